@@ -0,0 +1,230 @@
+use crate::text;
+use crate::vt::Style;
+use std::ops::Range;
+
+/// A run of text in one style.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+/// Splits program output into lines of styled text, ready to draw. Colours
+/// and attributes set by SGR sequences carry over to the lines after, as on
+/// a terminal; every other escape sequence (cursor movement, window titles)
+/// is dropped.
+pub fn parse(output: &str) -> Vec<Vec<Span>> {
+    let mut lines = Vec::new();
+    let mut line: Vec<Span> = Vec::new();
+    let mut style = Style::default();
+    let mut chars = output.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    // Parameter and intermediate bytes up to the final byte
+                    let mut params = String::new();
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            if c == 'm' {
+                                style.apply_sgr(&sgr_params(&params));
+                            }
+                            break;
+                        }
+                        params.push(c);
+                    }
+                }
+                // Operating system commands end with BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                Some('(' | ')' | '*' | '+') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\n' => lines.push(finish(std::mem::take(&mut line))),
+            c => match line.last_mut().filter(|span| span.style == style) {
+                Some(span) => span.text.push(c),
+                None => line.push(Span { text: c.to_string(), style }),
+            },
+        }
+    }
+    if !line.is_empty() {
+        lines.push(finish(line));
+    }
+    lines
+}
+
+/// Cuts a line to at most `max` columns, ending it with `…` in the style of
+/// the last span kept when anything was left out.
+pub fn truncate(spans: &[Span], max: usize) -> Vec<Span> {
+    let total: usize = spans.iter().map(|span| text::width(&span.text)).sum();
+    if total <= max {
+        return spans.to_vec();
+    }
+    if max == 0 {
+        return Vec::new();
+    }
+    let mut out: Vec<Span> = Vec::new();
+    let mut room = max.saturating_sub(1);
+    for span in spans {
+        let (text, used) = text::take_width(&span.text, room);
+        room -= used;
+        let cut = text.len() < span.text.len();
+        out.push(Span { text, style: span.style });
+        if cut {
+            break;
+        }
+    }
+    if let Some(last) = out.last_mut() {
+        last.text.push('…');
+    }
+    out
+}
+
+/// The text of a line without its styles.
+pub fn plain_text(spans: &[Span]) -> String {
+    spans.iter().map(|span| span.text.as_str()).collect()
+}
+
+/// Restyles the parts of a line at the given byte ranges of its plain
+/// text, e.g. search matches.
+pub fn highlight(spans: &[Span], ranges: &[Range<usize>], restyle: impl Fn(Style) -> Style) -> Vec<Span> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    for span in spans {
+        let end = start + span.text.len();
+        let mut cuts = vec![start, end];
+        for range in ranges {
+            cuts.extend([range.start, range.end].into_iter().filter(|&cut| cut > start && cut < end));
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+        for cut in cuts.windows(2) {
+            let inside = ranges.iter().any(|range| range.start <= cut[0] && cut[1] <= range.end);
+            out.push(Span {
+                text: span.text[cut[0] - start..cut[1] - start].to_string(),
+                style: if inside { restyle(span.style) } else { span.style },
+            });
+        }
+        start = end;
+    }
+    out
+}
+
+fn sgr_params(params: &str) -> Vec<u16> {
+    params
+        .split(';')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Shows a line the way a terminal would end up showing it: a carriage
+/// return starts over (progress bars redraw themselves that way), and the
+/// remaining control characters are escaped.
+fn finish(spans: Vec<Span>) -> Vec<Span> {
+    let mut spans = spans;
+    // Carriage returns at the end of the line, as in `\r\n`, move nothing
+    while let Some(last) = spans.last_mut() {
+        let trimmed = last.text.trim_end_matches('\r').len();
+        last.text.truncate(trimmed);
+        if !last.text.is_empty() {
+            break;
+        }
+        spans.pop();
+    }
+    if let Some(last) = spans.iter().rposition(|span| span.text.contains('\r')) {
+        spans.drain(..last);
+        let after = spans[0].text.rsplit('\r').next().unwrap_or_default().to_string();
+        spans[0].text = after;
+        if spans[0].text.is_empty() {
+            spans.remove(0);
+        }
+    }
+
+    let mut column = 0;
+    for span in &mut spans {
+        span.text = text::escape_controls(&span.text, &mut column);
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sgr(params: &[u16]) -> Style {
+        let mut style = Style::default();
+        style.apply_sgr(params);
+        style
+    }
+
+    fn span(text: &str, style: Style) -> Span {
+        Span { text: text.to_string(), style }
+    }
+
+    #[test]
+    fn splits_lines_and_carries_styles_over() {
+        let red = sgr(&[31]);
+        let lines = parse("a\x1b[31mb\nc\x1b[0md\n");
+        assert_eq!(lines, [vec![span("a", Style::default()), span("b", red)], vec![span("c", red), span("d", Style::default())]]);
+        assert_eq!(parse("no newline"), [vec![span("no newline", Style::default())]]);
+        assert_eq!(parse("\n\n").len(), 2);
+    }
+
+    #[test]
+    fn drops_other_escape_sequences() {
+        let lines = parse("\x1b]0;title\x07\x1b[2Ja\x1b(Bb\x1b]8;;url\x1b\\c\n");
+        assert_eq!(lines, [vec![span("abc", Style::default())]]);
+        assert_eq!(parse("\x1b[1;4mx")[0], [span("x", sgr(&[1, 4]))]);
+    }
+
+    #[test]
+    fn carriage_returns_start_the_line_over() {
+        assert_eq!(parse("10%\r50%\r100%\r\n")[0], [span("100%", Style::default())]);
+        let lines = parse("\x1b[32m10%\r\x1b[0mdone\n");
+        assert_eq!(lines[0], [span("done", Style::default())]);
+        assert_eq!(parse("a\tb\x07\n")[0], [span("a       b^G", Style::default())]);
+    }
+
+    #[test]
+    fn highlight_restyles_ranges_across_spans() {
+        let red = sgr(&[31]);
+        let line = [span("hello ", Style::default()), span("world", red)];
+        let marked = |style: Style| Style { reverse: true, ..style };
+        let out = highlight(&line, &[4..8, 9..10], marked);
+        assert_eq!(
+            out,
+            [
+                span("hell", Style::default()),
+                span("o ", marked(Style::default())),
+                span("wo", marked(red)),
+                span("r", red),
+                span("l", marked(red)),
+                span("d", red),
+            ]
+        );
+        assert_eq!(plain_text(&out), "hello world");
+        assert_eq!(highlight(&line, &[], marked), line);
+    }
+
+    #[test]
+    fn truncate_keeps_the_last_style() {
+        let red = sgr(&[31]);
+        let line = [span("ab", Style::default()), span("cdef", red)];
+        assert_eq!(truncate(&line, 6), line);
+        assert_eq!(truncate(&line, 4), [span("ab", Style::default()), span("c…", red)]);
+        assert_eq!(truncate(&line, 0), []);
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::{self, Write};
+
+/// The most text sent in one go: its encoding stays under 100 kB, which
+/// terminals accept, while many silently drop longer OSC 52 sequences.
+const MAX_BYTES: usize = 100_000 / 4 * 3;
+
+/// Puts text on the system clipboard through the terminal with OSC 52,
+/// which also works over ssh. Terminals that do not support it ignore it.
+/// Text over `MAX_BYTES` is refused with an `InvalidInput` error.
+pub fn copy(text: &str) -> io::Result<()> {
+    if text.len() > MAX_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} kB is too large for the clipboard (at most {} kB)", text.len().div_ceil(1000), MAX_BYTES / 1000),
+        ));
+    }
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &b)| group | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_the_rfc_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (input, encoded) in vectors {
+            assert_eq!(base64(input.as_bytes()), encoded);
+        }
+    }
+
+    #[test]
+    fn base64_uses_the_whole_alphabet() {
+        assert_eq!(base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+        assert_eq!(base64(&[0, 0, 0]), "AAAA");
+        assert_eq!(base64("ä\n".as_bytes()), "w6QK");
+    }
+
+    #[test]
+    fn copy_refuses_text_the_terminal_would_drop() {
+        let err = copy(&"x".repeat(MAX_BYTES + 1)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "76 kB is too large for the clipboard (at most 75 kB)");
+        assert!(base64(&[0; MAX_BYTES]).len() <= 100_000);
+    }
+}
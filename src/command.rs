@@ -0,0 +1,126 @@
+/// A command typed on the `:` command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExCommand {
+    Run(String),
+    Add,
+    Delete(String),
+    Set(String, String),
+    Reload,
+    /// Writes the config, or with a file name the output being viewed;
+    /// `:w!` overwrites a file that exists.
+    Write(Option<String>, bool),
+    Quit,
+    WriteQuit,
+    Help,
+}
+
+/// Command names in the order completion offers them.
+const COMMANDS: &[&str] = &["run", "add", "delete", "set", "reload", "write", "quit", "wq", "help"];
+
+/// Values `:set` accepts, for completion.
+const SETTINGS: &[&str] = &["sort=name", "sort=usage"];
+
+pub fn parse(line: &str) -> Result<ExCommand, String> {
+    let line = line.trim();
+    let (name, argument) = match line.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (line, ""),
+    };
+    let required = |what: &str| {
+        if argument.is_empty() {
+            Err(format!("Argument required: {} {}", name, what))
+        } else {
+            Ok(argument.to_string())
+        }
+    };
+
+    match name {
+        "run" | "r" => required("<name>").map(ExCommand::Run),
+        "add" | "a" => Ok(ExCommand::Add),
+        "delete" | "d" => required("<name>").map(ExCommand::Delete),
+        "set" | "se" => {
+            let setting = required("<option>=<value>")?;
+            match setting.split_once('=') {
+                Some((option, value)) => Ok(ExCommand::Set(option.trim().to_string(), value.trim().to_string())),
+                None => Err(format!("Expected <option>=<value>: {}", setting)),
+            }
+        }
+        "reload" | "e" => Ok(ExCommand::Reload),
+        "write" | "w" | "write!" | "w!" => {
+            let file = (!argument.is_empty()).then(|| argument.to_string());
+            Ok(ExCommand::Write(file, name.ends_with('!')))
+        }
+        "quit" | "q" | "q!" => Ok(ExCommand::Quit),
+        "wq" | "x" => Ok(ExCommand::WriteQuit),
+        "help" | "h" => Ok(ExCommand::Help),
+        "" => Err("No command".to_string()),
+        _ => Err(format!("Not a command: {}", name)),
+    }
+}
+
+/// Whole command lines that complete `line`: command names for the first
+/// word, entry names after `run` and `delete`, settings after `set`.
+pub fn complete(line: &str, names: &[&str]) -> Vec<String> {
+    let Some((command, partial)) = line.split_once(' ') else {
+        return COMMANDS
+            .iter()
+            .filter(|name| name.starts_with(line))
+            .map(|name| name.to_string())
+            .collect();
+    };
+
+    let candidates: &[&str] = match command {
+        "run" | "r" | "delete" | "d" => names,
+        "set" | "se" => SETTINGS,
+        _ => &[],
+    };
+    candidates
+        .iter()
+        .filter(|candidate| candidate.starts_with(partial.trim_start()))
+        .map(|candidate| format!("{} {}", command, candidate))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands_and_abbreviations() {
+        assert_eq!(parse("run backup"), Ok(ExCommand::Run("backup".to_string())));
+        assert_eq!(parse("  r   two words  "), Ok(ExCommand::Run("two words".to_string())));
+        assert_eq!(parse("d old"), Ok(ExCommand::Delete("old".to_string())));
+        assert_eq!(parse("set sort = usage"), Ok(ExCommand::Set("sort".to_string(), "usage".to_string())));
+        assert_eq!(parse("w"), Ok(ExCommand::Write(None, false)));
+        assert_eq!(parse("write out.txt"), Ok(ExCommand::Write(Some("out.txt".to_string()), false)));
+        assert_eq!(parse("w! out.txt"), Ok(ExCommand::Write(Some("out.txt".to_string()), true)));
+        assert_eq!(parse("q!"), Ok(ExCommand::Quit));
+        assert_eq!(parse("x"), Ok(ExCommand::WriteQuit));
+        assert_eq!(parse("e"), Ok(ExCommand::Reload));
+    }
+
+    #[test]
+    fn reports_missing_arguments_and_unknown_commands() {
+        assert_eq!(parse("run"), Err("Argument required: run <name>".to_string()));
+        assert_eq!(parse("set sort"), Err("Expected <option>=<value>: sort".to_string()));
+        assert_eq!(parse("frobnicate"), Err("Not a command: frobnicate".to_string()));
+        assert_eq!(parse("   "), Err("No command".to_string()));
+    }
+
+    #[test]
+    fn completes_command_names() {
+        assert_eq!(complete("", &[]).len(), COMMANDS.len());
+        assert_eq!(complete("w", &[]), ["write", "wq"]);
+        assert_eq!(complete("re", &[]), ["reload"]);
+        assert!(complete("z", &[]).is_empty());
+    }
+
+    #[test]
+    fn completes_arguments() {
+        let names = ["backup", "build", "deploy"];
+        assert_eq!(complete("run b", &names), ["run backup", "run build"]);
+        assert_eq!(complete("d  de", &names), ["d deploy"]);
+        assert_eq!(complete("set sort=u", &names), ["set sort=usage"]);
+        assert!(complete("quit b", &names).is_empty());
+    }
+}
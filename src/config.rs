@@ -1,22 +1,254 @@
+use crate::container;
+use crate::keymap::{Action, Hotkey, KeyMode, Keymap};
+use crate::privilege::{Backend, Elevation};
+use crate::ssh::SshHost;
+use crate::template;
+use crate::theme::{Theme, ThemeSpec};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProgramEntry {
     pub name: String,
     pub display_name: String,
+    #[serde(default)]
     pub command: String,
+    #[serde(default)]
     pub args: Vec<String>,
+    #[serde(default)]
     pub description: Option<String>,
+    #[serde(default)]
     pub run_with_sudo: bool,
+    #[serde(default)]
     pub show_output: bool,
+    /// Seconds a captured run may take before it is terminated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Run inside a pane of the dashboard instead of taking over the terminal.
+    #[serde(default)]
+    pub embedded: bool,
+    /// Commands run in order instead of `command`, making this a workflow.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<Step>,
+    #[serde(default, skip_serializing_if = "FailurePolicy::is_default")]
+    pub on_failure: FailurePolicy,
+    /// Names of entries that must run (or pass their check) first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Health check that, when it succeeds, lets a dependent skip this entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check: Option<Step>,
+    /// SSH host (or `~/.ssh/config` alias) to run the program on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Several SSH hosts to run the program on in parallel.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    /// How many hosts run at the same time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel: Option<usize>,
+    /// Container to run the program in, or `"?"` to pick one at launch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// Log to follow from the dashboard.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs: Option<LogSource>,
+    /// Account to run as, `user`, `user:group` or `:group`; implies elevation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as: Option<String>,
+    /// Privilege backend for this entry instead of the global one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub privilege: Option<Backend>,
+    /// Key that launches the entry from the list, e.g. `1` or `<leader>b`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hotkey: Option<String>,
+    /// Label for related entries, set from the batch actions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Asks captured commands for colour even though their output is not a
+    /// terminal.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub color: bool,
+}
+
+impl ProgramEntry {
+    pub fn is_workflow(&self) -> bool {
+        !self.steps.is_empty()
+    }
+
+    /// Hosts named by the entry itself; empty when it follows the active host.
+    pub fn targets(&self) -> Vec<&str> {
+        if self.hosts.is_empty() {
+            self.host.iter().map(String::as_str).collect()
+        } else {
+            self.hosts.iter().map(String::as_str).collect()
+        }
+    }
+
+    pub fn picks_container(&self) -> bool {
+        self.container.as_deref() == Some(container::PICK)
+    }
+}
+
+/// A machine in the host inventory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostEntry {
+    pub name: String,
+    pub address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub run_with_sudo: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+impl Step {
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None if self.args.is_empty() => self.command.clone(),
+            None => format!("{} {}", self.command, self.args.join(" ")),
+        }
+    }
+}
+
+/// Where an entry's log comes from: `{"unit": "nginx.service"}` for the
+/// journal of a systemd unit, or `{"file": "/var/log/syslog"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSource {
+    Unit(String),
+    File(String),
+}
+
+/// What a workflow does when one of its steps fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailurePolicy {
+    #[default]
+    Stop,
+    Continue,
+}
+
+impl FailurePolicy {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The line under the top bar: `"text"`, a list of lines shown in turn, or
+/// `{"command": "fortune", "args": ["-s"]}` for the output of a command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Quote {
+    Text(String),
+    List(Vec<String>),
+    Command {
+        command: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+    },
+}
+
+impl Default for Quote {
+    fn default() -> Self {
+        Quote::Text("\"L'homme est condamné à être libre.\" - Sartre".to_string())
+    }
+}
+
+/// Where the main screen shows the system metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsLayout {
+    /// A column right of the list; the header line when the terminal is
+    /// too narrow for it.
+    Sidebar,
+    /// One line under the top bar.
+    Header,
+}
+
+/// Order of the entry list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Alphabetical by display name.
+    #[default]
+    Name,
+    /// Most launched first.
+    Usage,
+}
+
+impl SortOrder {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
     pub programs: HashMap<String, ProgramEntry>,
+    /// Timeout applied to captured runs of entries without their own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub hosts: HashMap<String, HostEntry>,
+    /// Privilege backend for entries without their own; sudo when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub privilege: Option<Backend>,
+    #[serde(default, skip_serializing_if = "SortOrder::is_default")]
+    pub sort: SortOrder,
+    /// Launch counts per entry, kept in their own file.
+    #[serde(skip)]
+    pub usage: HashMap<String, u64>,
+    /// Container CLI (`docker` or `podman`); detected when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_engine: Option<String>,
+    /// Key bindings per mode that replace or add to the defaults.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub keys: HashMap<KeyMode, HashMap<String, Action>>,
+    /// Key that starts `<leader>` hotkeys; `\` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leader: Option<String>,
+    /// Built-in or user theme to draw with; `dark` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub themes: HashMap<String, ThemeSpec>,
+    /// Top bar template of the main screen; the title when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Bottom bar template of the main screen; `{keys}` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+    /// Line under the top bar; the Sartre quote when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<Quote>,
+    /// System metrics on the main screen; none when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<MetricsLayout>,
+    /// Clicks and the scroll wheel; off leaves selecting text to the
+    /// terminal.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub mouse: bool,
 }
 
 impl Default for Config {
@@ -34,6 +266,22 @@ impl Default for Config {
                 description: Some("System resource monitor".to_string()),
                 run_with_sudo: false,
                 show_output: false,
+                timeout: None,
+                embedded: false,
+                steps: vec![],
+                on_failure: FailurePolicy::Stop,
+                depends_on: vec![],
+                check: None,
+                host: None,
+                hosts: vec![],
+                parallel: None,
+                container: None,
+                logs: None,
+                run_as: None,
+                privilege: None,
+                hotkey: None,
+                group: None,
+                color: false,
             },
         );
         
@@ -47,10 +295,43 @@ impl Default for Config {
                 description: Some("Vim text editor".to_string()),
                 run_with_sudo: false,
                 show_output: false,
+                timeout: None,
+                embedded: false,
+                steps: vec![],
+                on_failure: FailurePolicy::Stop,
+                depends_on: vec![],
+                check: None,
+                host: None,
+                hosts: vec![],
+                parallel: None,
+                container: None,
+                logs: None,
+                run_as: None,
+                privilege: None,
+                hotkey: None,
+                group: None,
+                color: false,
             },
         );
 
-        Self { programs }
+        Self {
+            programs,
+            default_timeout: None,
+            hosts: HashMap::new(),
+            container_engine: None,
+            privilege: None,
+            sort: SortOrder::Name,
+            usage: HashMap::new(),
+            keys: HashMap::new(),
+            leader: None,
+            theme: None,
+            themes: HashMap::new(),
+            header: None,
+            footer: None,
+            quote: None,
+            metrics: None,
+            mouse: false,
+        }
     }
 }
 
@@ -70,7 +351,9 @@ impl Config {
         
         if config_file.exists() {
             let content = fs::read_to_string(&config_file)?;
-            let config: Config = serde_json::from_str(&content)?;
+            let mut config: Config = serde_json::from_str(&content)?;
+            config.validate()?;
+            config.usage = Self::load_usage();
             Ok(config)
         } else {
             let config = Config::default();
@@ -90,6 +373,40 @@ impl Config {
         Ok(())
     }
 
+    /// Writes the named entries to `path` in the shape of a config file,
+    /// so they can be copied into another one.
+    pub fn export(&self, names: &[&str], path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(Serialize)]
+        struct Export<'a> {
+            programs: HashMap<&'a str, &'a ProgramEntry>,
+        }
+
+        let programs = names.iter().filter_map(|name| self.programs.get(*name).map(|p| (*name, p))).collect();
+        let content = serde_json::to_string_pretty(&Export { programs })?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn usage_file() -> PathBuf {
+        Self::config_dir().join("usage.json")
+    }
+
+    /// Launch counts are only used for sorting, so a missing or unreadable
+    /// file just starts them over.
+    fn load_usage() -> HashMap<String, u64> {
+        fs::read_to_string(Self::usage_file())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn record_launch(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        *self.usage.entry(name.to_string()).or_default() += 1;
+        fs::create_dir_all(Self::config_dir())?;
+        fs::write(Self::usage_file(), serde_json::to_string_pretty(&self.usage)?)?;
+        Ok(())
+    }
+
     pub fn add_program(&mut self, entry: ProgramEntry) {
         self.programs.insert(entry.name.clone(), entry);
     }
@@ -98,9 +415,296 @@ impl Config {
         self.programs.remove(name).is_some()
     }
 
+    /// How a command of an entry gains privileges, if it needs to. All
+    /// commands of an entry share its backend.
+    pub fn elevation(&self, program: &ProgramEntry, run_with_sudo: bool, run_as: Option<&str>) -> Option<Elevation> {
+        (run_with_sudo || run_as.is_some()).then(|| Elevation {
+            backend: program.privilege.or(self.privilege).unwrap_or_default(),
+            run_as: run_as.map(String::from),
+        })
+    }
+
+    pub fn entry_elevation(&self, program: &ProgramEntry) -> Option<Elevation> {
+        self.elevation(program, program.run_with_sudo, program.run_as.as_deref())
+    }
+
+    pub fn step_elevation(&self, program: &ProgramEntry, step: &Step) -> Option<Elevation> {
+        self.elevation(program, step.run_with_sudo, step.run_as.as_deref())
+    }
+
+    pub fn hotkey(&self, program: &ProgramEntry) -> Option<Hotkey> {
+        Hotkey::parse(program.hotkey.as_deref()?).ok()
+    }
+
+    pub fn theme(&self) -> Result<Theme, String> {
+        Theme::load(self.theme.as_deref(), &self.themes)
+    }
+
+    pub fn keymap(&self) -> Result<Keymap, String> {
+        Keymap::new(&self.keys, self.leader.as_deref())
+    }
+
+    pub fn timeout_for(&self, program: &ProgramEntry) -> Option<Duration> {
+        program.timeout.or(self.default_timeout).map(Duration::from_secs)
+    }
+
+    /// Checks what the file format cannot: that keys match the names, that
+    /// every `depends_on` names an existing entry and no entry depends on
+    /// itself, directly or through others, that `run_as` groups suit the
+    /// backend, that host tags exist, and that the templates, theme, keys
+    /// and hotkeys are valid.
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // Entries and hosts are looked up by name everywhere, so the key
+        // must be it
+        for (key, program) in &self.programs {
+            if *key != program.name {
+                return Err(format!("Program '{}' has name '{}'; the two must match", key, program.name).into());
+            }
+        }
+        for (key, host) in &self.hosts {
+            if *key != host.name {
+                return Err(format!("Host '{}' has name '{}'; the two must match", key, host.name).into());
+            }
+        }
+
+        for program in self.get_programs() {
+            for dependency in &program.depends_on {
+                if !self.programs.contains_key(dependency) {
+                    return Err(format!("{} depends on unknown program '{}'", program.name, dependency).into());
+                }
+                if self.programs[dependency].picks_container() {
+                    return Err(format!("{} depends on '{}', which picks its container at launch", program.name, dependency).into());
+                }
+            }
+        }
+
+        for program in self.get_programs() {
+            let backend = program.privilege.or(self.privilege).unwrap_or_default();
+            let steps = program.steps.iter().chain(&program.check);
+            let mut run_as = program.run_as.iter().chain(steps.filter_map(|step| step.run_as.as_ref()));
+            if !backend.supports_group() && run_as.any(|run_as| run_as.contains(':')) {
+                return Err(format!("{} runs as a group, which {} cannot do", program.name, backend.name()).into());
+            }
+        }
+
+        for program in self.get_programs() {
+            for tag in program.targets().iter().filter_map(|t| t.strip_prefix('@')) {
+                if self.hosts_tagged(tag).is_empty() {
+                    return Err(format!("{} runs on tag '{}' but no host has it", program.name, tag).into());
+                }
+            }
+        }
+
+        let quotes = match &self.quote {
+            Some(Quote::Text(text)) => vec![text],
+            Some(Quote::List(lines)) => lines.iter().collect(),
+            _ => vec![],
+        };
+        for template in self.header.iter().chain(&self.footer).chain(quotes) {
+            template::check(template).map_err(|e| format!("'{}': {}", template, e))?;
+        }
+
+        self.theme()?;
+        let keymap = self.keymap()?;
+        let mut hotkeys = HashMap::new();
+        for program in self.get_programs() {
+            let Some(text) = &program.hotkey else { continue };
+            let hotkey = Hotkey::parse(text).map_err(|e| format!("{}: {}", program.name, e))?;
+            keymap.check_hotkey(hotkey).map_err(|e| format!("{} has hotkey {}: {}", program.name, text, e))?;
+            if let Some(other) = hotkeys.insert(hotkey, &program.name) {
+                return Err(format!("{} and {} have the same hotkey {}", other, program.name, text).into());
+            }
+        }
+
+        let mut checked = HashSet::new();
+        for name in self.programs.keys() {
+            self.check_cycles(name, &mut Vec::new(), &mut checked)?;
+        }
+        Ok(())
+    }
+
+    fn check_cycles<'a>(
+        &'a self,
+        name: &'a str,
+        path: &mut Vec<&'a str>,
+        checked: &mut HashSet<&'a str>,
+    ) -> Result<(), String> {
+        if checked.contains(name) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|n| *n == name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name);
+            return Err(format!("Dependency cycle: {}", cycle.join(" -> ")));
+        }
+
+        path.push(name);
+        let dependencies = self.programs.get(name).map_or(&[][..], |program| &program.depends_on[..]);
+        for dependency in dependencies {
+            self.check_cycles(dependency, path, checked)?;
+        }
+        path.pop();
+        checked.insert(name);
+        Ok(())
+    }
+
+    /// All entries that must run before `program`, each listed after its own
+    /// prerequisites.
+    pub fn prerequisites(&self, program: &ProgramEntry) -> Vec<&ProgramEntry> {
+        let mut order = Vec::new();
+        let mut seen = HashSet::new();
+        for dependency in &program.depends_on {
+            if let Some((name, _)) = self.programs.get_key_value(dependency) {
+                self.collect_prerequisites(name, &mut seen, &mut order);
+            }
+        }
+        order
+    }
+
+    fn collect_prerequisites<'a>(
+        &'a self,
+        name: &'a str,
+        seen: &mut HashSet<&'a str>,
+        order: &mut Vec<&'a ProgramEntry>,
+    ) {
+        if !seen.insert(name) {
+            return;
+        }
+        if let Some(program) = self.programs.get(name) {
+            for dependency in &program.depends_on {
+                self.collect_prerequisites(dependency, seen, order);
+            }
+            order.push(program);
+        }
+    }
+
+    /// Entries that list `name` in their `depends_on`. `depends_on` holds
+    /// keys of `programs`; `validate` makes sure they are also the names.
+    pub fn dependents(&self, name: &str) -> Vec<&ProgramEntry> {
+        self.get_programs()
+            .into_iter()
+            .filter(|p| p.depends_on.iter().any(|d| d == name))
+            .collect()
+    }
+
+    pub fn get_hosts(&self) -> Vec<&HostEntry> {
+        let mut hosts: Vec<_> = self.hosts.values().collect();
+        hosts.sort_by(|a, b| a.name.cmp(&b.name));
+        hosts
+    }
+
+    fn hosts_tagged(&self, tag: &str) -> Vec<&HostEntry> {
+        self.get_hosts().into_iter().filter(|h| h.tags.iter().any(|t| t == tag)).collect()
+    }
+
+    /// Inventory hosts connect to their address; any other name is handed
+    /// to ssh as is, so `~/.ssh/config` aliases keep working.
+    pub fn resolve_host(&self, name: &str) -> SshHost {
+        match self.hosts.get(name) {
+            Some(host) => SshHost {
+                alias: host.name.clone(),
+                hostname: Some(host.address.clone()),
+                user: host.user.clone(),
+                port: host.port,
+            },
+            None => SshHost {
+                alias: name.to_string(),
+                hostname: None,
+                user: None,
+                port: None,
+            },
+        }
+    }
+
+    /// Hosts an entry runs on: its own `host`/`hosts`, where `@tag` stands
+    /// for every inventory host with that tag, or else the active host.
+    /// Empty means the entry runs locally.
+    pub fn targets(&self, program: &ProgramEntry, active_host: Option<&str>) -> Vec<SshHost> {
+        let mut names: Vec<&str> = Vec::new();
+        for target in program.targets() {
+            let expanded = match target.strip_prefix('@') {
+                Some(tag) => self.hosts_tagged(tag).into_iter().map(|h| h.name.as_str()).collect(),
+                None => vec![target],
+            };
+            for name in expanded {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        if program.targets().is_empty() {
+            names.extend(active_host);
+        }
+        names.into_iter().map(|name| self.resolve_host(name)).collect()
+    }
+
     pub fn get_programs(&self) -> Vec<&ProgramEntry> {
         let mut programs: Vec<_> = self.programs.values().collect();
         programs.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+        if self.sort == SortOrder::Usage {
+            let usage = |program: &ProgramEntry| self.usage.get(&program.name).copied().unwrap_or(0);
+            // Stable, so equally used entries stay alphabetical
+            programs.sort_by_key(|program| std::cmp::Reverse(usage(program)));
+        }
         programs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config whose entries depend on each other as given.
+    fn config(dependencies: &[(&str, &[&str])]) -> Config {
+        let programs: serde_json::Map<_, _> = dependencies
+            .iter()
+            .map(|(name, depends_on)| {
+                let entry = serde_json::json!({"name": name, "display_name": name, "command": "true", "depends_on": depends_on});
+                (name.to_string(), entry)
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({ "programs": programs })).unwrap()
+    }
+
+    fn names(programs: Vec<&ProgramEntry>) -> Vec<&str> {
+        programs.iter().map(|program| program.name.as_str()).collect()
+    }
+
+    #[test]
+    fn rejects_an_entry_depending_on_itself() {
+        let err = config(&[("a", &["a"])]).validate().unwrap_err();
+        assert_eq!(err.to_string(), "Dependency cycle: a -> a");
+    }
+
+    #[test]
+    fn rejects_a_cycle_through_other_entries() {
+        let err = config(&[("a", &["b"]), ("b", &["c"]), ("c", &["a"]), ("d", &["a"])]).validate().unwrap_err();
+        // The cycle is reported from whichever of its entries is checked first
+        let cycles = ["a -> b -> c -> a", "b -> c -> a -> b", "c -> a -> b -> c"];
+        assert!(cycles.iter().any(|cycle| err.to_string() == format!("Dependency cycle: {}", cycle)), "{}", err);
+    }
+
+    #[test]
+    fn rejects_an_unknown_dependency() {
+        let err = config(&[("a", &["missing"])]).validate().unwrap_err();
+        assert_eq!(err.to_string(), "a depends on unknown program 'missing'");
+    }
+
+    #[test]
+    fn rejects_a_key_other_than_the_name() {
+        let mut config = config(&[("a", &[])]);
+        let entry = config.programs.remove("a").unwrap();
+        config.programs.insert("b".to_string(), entry);
+        assert_eq!(config.validate().unwrap_err().to_string(), "Program 'b' has name 'a'; the two must match");
+    }
+
+    #[test]
+    fn shared_prerequisites_come_once_and_first() {
+        let config = config(&[("base", &[]), ("db", &["base"]), ("cache", &["base"]), ("app", &["db", "cache", "base"])]);
+        config.validate().unwrap();
+        assert_eq!(names(config.prerequisites(&config.programs["app"])), ["base", "db", "cache"]);
+        assert_eq!(names(config.prerequisites(&config.programs["db"])), ["base"]);
+        assert!(config.prerequisites(&config.programs["base"]).is_empty());
+        assert_eq!(names(config.dependents("base")), ["app", "cache", "db"]);
+    }
+}
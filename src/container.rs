@@ -0,0 +1,66 @@
+use std::env;
+use std::path::Path;
+
+/// The `container` value that asks for a container when the entry is launched.
+pub const PICK: &str = "?";
+
+/// A running container as listed by `ps`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Container {
+    pub name: String,
+    pub image: String,
+    pub status: String,
+}
+
+/// The container CLI to use: the configured one, otherwise `docker`, or
+/// `podman` when only podman is installed.
+pub fn engine(configured: Option<&str>) -> String {
+    if let Some(engine) = configured {
+        return engine.to_string();
+    }
+    if !in_path("docker") && in_path("podman") {
+        "podman".to_string()
+    } else {
+        "docker".to_string()
+    }
+}
+
+fn in_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|path| env::split_paths(&path).any(|dir| Path::new(&dir).join(program).is_file()))
+}
+
+/// Arguments to the engine that run a command inside a container. Runs
+/// that take over the terminal get a TTY and stdin.
+pub fn exec_args(container: &str, command: &str, args: &[String], interactive: bool) -> Vec<String> {
+    let mut words = vec!["exec".to_string()];
+    if interactive {
+        words.push("-it".to_string());
+    }
+    words.push(container.to_string());
+    words.push(command.to_string());
+    words.extend(args.iter().cloned());
+    words
+}
+
+/// Arguments to the engine that list running containers, one per line.
+pub fn list_args() -> Vec<String> {
+    ["ps", "--format", "{{.Names}}\t{{.Image}}\t{{.Status}}"].iter().map(|s| s.to_string()).collect()
+}
+
+pub fn parse_list(output: &str) -> Vec<Container> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.trim();
+            if name.is_empty() {
+                return None;
+            }
+            Some(Container {
+                name: name.to_string(),
+                image: fields.next().unwrap_or_default().to_string(),
+                status: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,726 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The screens that have their own key bindings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyMode {
+    Normal,
+    Help,
+    Output,
+    Progress,
+    Batch,
+    Systemd,
+    Logs,
+    /// The host and container choosers.
+    Picker,
+}
+
+/// Something a key can be bound to. Each mode accepts its own subset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    /// Removes the default binding of a key.
+    None,
+    Quit,
+    Close,
+    Cancel,
+    Down,
+    Up,
+    Last,
+    ScreenTop,
+    ScreenMiddle,
+    ScreenBottom,
+    JumpBack,
+    JumpForward,
+    Launch,
+    Progress,
+    FocusPane,
+    TogglePane,
+    Host,
+    Container,
+    Systemd,
+    Logs,
+    ToggleSelect,
+    Visual,
+    Add,
+    Delete,
+    Reload,
+    Sudo,
+    CommandLine,
+    Help,
+    Open,
+    Select,
+    /// Answers yes to a question, like deleting the selected programs.
+    Confirm,
+    Status,
+    Start,
+    Stop,
+    Restart,
+    FailedOnly,
+    Refresh,
+    Pin,
+    Pause,
+    Follow,
+    Filter,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    Yank,
+}
+
+impl Action {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Action::None => "Nothing",
+            Action::Quit => "Quit",
+            Action::Close => "Close",
+            Action::Cancel => "Cancel running program",
+            Action::Down => "Move down",
+            Action::Up => "Move up",
+            Action::Last => "Last program (with a count: that line)",
+            Action::ScreenTop => "Top of the screen",
+            Action::ScreenMiddle => "Middle of the screen",
+            Action::ScreenBottom => "Bottom of the screen",
+            Action::JumpBack => "Back in the jump list",
+            Action::JumpForward => "Forward in the jump list",
+            Action::Launch => "Launch selected program",
+            Action::Progress => "Show progress or host results",
+            Action::FocusPane => "Focus list / embedded program",
+            Action::TogglePane => "Background / restore embedded program",
+            Action::Host => "Switch the active host",
+            Action::Container => "Launch selected program in a container",
+            Action::Systemd => "Manage systemd units",
+            Action::Logs => "Follow logs",
+            Action::ToggleSelect => "Select / unselect for batch actions",
+            Action::Visual => "Select a range (again to keep it)",
+            Action::Add => "Add new program",
+            Action::Delete => "Delete selected program",
+            Action::Reload => "Reload configuration",
+            Action::Sudo => "Enter the sudo password ahead of time",
+            Action::CommandLine => "Command line (:run, :add, :delete, :set, :w, :q)",
+            Action::Help => "Show this help",
+            Action::Open => "Show output",
+            Action::Select => "Select",
+            Action::Confirm => "Confirm",
+            Action::Status => "Show status",
+            Action::Start => "Start",
+            Action::Stop => "Stop",
+            Action::Restart => "Restart",
+            Action::FailedOnly => "Show only failed units",
+            Action::Refresh => "Refresh",
+            Action::Pin => "Pin to the dashboard",
+            Action::Pause => "Pause / resume",
+            Action::Follow => "Follow new lines",
+            Action::Filter => "Filter by regular expression",
+            Action::Search => "Search by regular expression",
+            Action::NextMatch => "Next match",
+            Action::PreviousMatch => "Previous match",
+            Action::Yank => "Copy to the clipboard",
+        }
+    }
+}
+
+/// The normal-mode actions, grouped as on the help screen.
+pub const NORMAL_SECTIONS: &[(&str, &[Action])] = &[
+    (
+        "NAVIGATION",
+        &[
+            Action::Down,
+            Action::Up,
+            Action::Last,
+            Action::ScreenTop,
+            Action::ScreenMiddle,
+            Action::ScreenBottom,
+            Action::JumpBack,
+            Action::JumpForward,
+            Action::Launch,
+            Action::Cancel,
+            Action::Progress,
+            Action::FocusPane,
+            Action::TogglePane,
+            Action::Host,
+            Action::Container,
+            Action::Systemd,
+            Action::Logs,
+        ],
+    ),
+    ("SELECTION", &[Action::ToggleSelect, Action::Visual]),
+    ("PROGRAM MANAGEMENT", &[Action::Add, Action::Delete, Action::Reload]),
+    ("OTHER", &[Action::Sudo, Action::CommandLine, Action::Help, Action::Quit]),
+];
+
+impl KeyMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyMode::Normal => "normal",
+            KeyMode::Help => "help",
+            KeyMode::Output => "output",
+            KeyMode::Progress => "progress",
+            KeyMode::Batch => "batch",
+            KeyMode::Systemd => "systemd",
+            KeyMode::Logs => "logs",
+            KeyMode::Picker => "picker",
+        }
+    }
+
+    /// Actions that can be bound in this mode.
+    pub fn actions(self) -> Vec<Action> {
+        match self {
+            KeyMode::Normal => NORMAL_SECTIONS.iter().flat_map(|(_, actions)| actions.iter().copied()).collect(),
+            KeyMode::Help => vec![Action::Close],
+            KeyMode::Output => vec![
+                Action::Down,
+                Action::Up,
+                Action::Last,
+                Action::Search,
+                Action::NextMatch,
+                Action::PreviousMatch,
+                Action::Yank,
+                Action::CommandLine,
+                Action::Close,
+            ],
+            KeyMode::Progress => vec![Action::Close, Action::Cancel],
+            KeyMode::Batch => vec![Action::Down, Action::Up, Action::Open, Action::Cancel, Action::Close],
+            KeyMode::Systemd => vec![
+                Action::Down,
+                Action::Up,
+                Action::Status,
+                Action::Logs,
+                Action::Start,
+                Action::Stop,
+                Action::Restart,
+                Action::FailedOnly,
+                Action::Refresh,
+                Action::Pin,
+                Action::Close,
+            ],
+            KeyMode::Logs => vec![Action::Pause, Action::Down, Action::Up, Action::Follow, Action::Filter, Action::Close],
+            KeyMode::Picker => vec![Action::Down, Action::Up, Action::Select, Action::Confirm, Action::Close],
+        }
+    }
+
+    fn defaults(self) -> &'static [(&'static str, Action)] {
+        match self {
+            KeyMode::Normal => &[
+                ("j", Action::Down),
+                ("Down", Action::Down),
+                ("k", Action::Up),
+                ("Up", Action::Up),
+                ("G", Action::Last),
+                ("H", Action::ScreenTop),
+                ("M", Action::ScreenMiddle),
+                ("L", Action::ScreenBottom),
+                ("Ctrl-O", Action::JumpBack),
+                ("Tab", Action::JumpForward),
+                ("Enter", Action::Launch),
+                ("x", Action::Cancel),
+                ("Ctrl-C", Action::Cancel),
+                ("p", Action::Progress),
+                ("Ctrl-W", Action::FocusPane),
+                ("b", Action::TogglePane),
+                ("@", Action::Host),
+                ("c", Action::Container),
+                ("s", Action::Systemd),
+                ("l", Action::Logs),
+                ("Space", Action::ToggleSelect),
+                ("V", Action::Visual),
+                ("a", Action::Add),
+                ("d", Action::Delete),
+                ("r", Action::Reload),
+                ("v", Action::Sudo),
+                (":", Action::CommandLine),
+                ("h", Action::Help),
+                ("F1", Action::Help),
+                ("q", Action::Quit),
+                ("Esc", Action::Quit),
+            ],
+            KeyMode::Help => &[("Esc", Action::Close), ("q", Action::Close), ("h", Action::Close), ("F1", Action::Close)],
+            KeyMode::Output => &[
+                ("j", Action::Down),
+                ("Down", Action::Down),
+                ("k", Action::Up),
+                ("Up", Action::Up),
+                ("G", Action::Last),
+                ("/", Action::Search),
+                ("n", Action::NextMatch),
+                ("N", Action::PreviousMatch),
+                ("y", Action::Yank),
+                (":", Action::CommandLine),
+                ("Space", Action::Close),
+                ("Esc", Action::Close),
+                ("q", Action::Close),
+            ],
+            KeyMode::Progress => &[
+                ("Esc", Action::Close),
+                ("q", Action::Close),
+                ("p", Action::Close),
+                ("x", Action::Cancel),
+                ("Ctrl-C", Action::Cancel),
+            ],
+            KeyMode::Batch => &[
+                ("j", Action::Down),
+                ("Down", Action::Down),
+                ("k", Action::Up),
+                ("Up", Action::Up),
+                ("Enter", Action::Open),
+                ("x", Action::Cancel),
+                ("Ctrl-C", Action::Cancel),
+                ("Esc", Action::Close),
+                ("q", Action::Close),
+            ],
+            KeyMode::Systemd => &[
+                ("j", Action::Down),
+                ("Down", Action::Down),
+                ("k", Action::Up),
+                ("Up", Action::Up),
+                ("Enter", Action::Status),
+                ("l", Action::Logs),
+                ("s", Action::Start),
+                ("t", Action::Stop),
+                ("r", Action::Restart),
+                ("f", Action::FailedOnly),
+                ("u", Action::Refresh),
+                ("+", Action::Pin),
+                ("Esc", Action::Close),
+                ("q", Action::Close),
+            ],
+            KeyMode::Logs => &[
+                ("Space", Action::Pause),
+                ("p", Action::Pause),
+                ("j", Action::Down),
+                ("Down", Action::Down),
+                ("k", Action::Up),
+                ("Up", Action::Up),
+                ("G", Action::Follow),
+                ("End", Action::Follow),
+                ("/", Action::Filter),
+                ("Esc", Action::Close),
+                ("q", Action::Close),
+            ],
+            KeyMode::Picker => &[
+                ("j", Action::Down),
+                ("Down", Action::Down),
+                ("k", Action::Up),
+                ("Up", Action::Up),
+                ("Enter", Action::Select),
+                ("y", Action::Confirm),
+                ("Y", Action::Confirm),
+                ("Esc", Action::Close),
+                ("q", Action::Close),
+            ],
+        }
+    }
+}
+
+/// A key with its modifiers, written like `q`, `G`, `Ctrl-W`, `Alt-x`,
+/// `Enter` or `F1` in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Shift is part of the character itself, so it is dropped for
+    /// character keys; `G` and `Shift-g` are the same chord.
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers = modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+
+    pub fn from_event(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+
+    /// The key press of this chord, for acting on it without the keyboard.
+    pub fn to_event(self) -> KeyEvent {
+        KeyEvent::new(self.code, self.modifiers)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut rest = text;
+        let mut modifiers = KeyModifiers::NONE;
+        // A lone "-" or a chord ending in "-" names the minus key
+        while let Some((prefix, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match prefix.to_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(format!("Invalid key '{}': unknown modifier '{}'", text, prefix)),
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            // Arrows as the bars show them
+            (Some('↑'), None) => KeyCode::Up,
+            (Some('↓'), None) => KeyCode::Down,
+            (Some('←'), None) => KeyCode::Left,
+            (Some('→'), None) => KeyCode::Right,
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                "enter" | "return" => KeyCode::Enter,
+                "space" => KeyCode::Char(' '),
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("Invalid key '{}'", text)),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "BackTab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            KeyCode::Delete => write!(f, "Delete"),
+            KeyCode::Insert => write!(f, "Insert"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+/// A key that launches an entry from the normal mode: a single key, or the
+/// leader key followed by one (`<leader>b`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hotkey {
+    Key(KeyChord),
+    Leader(KeyChord),
+}
+
+impl Hotkey {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.get(..8) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("<leader>") => Ok(Hotkey::Leader(KeyChord::parse(&text[8..])?)),
+            _ => Ok(Hotkey::Key(KeyChord::parse(text)?)),
+        }
+    }
+}
+
+/// Keys the normal mode reads before the keymap: counts, `gg` and marks.
+fn is_fixed(chord: KeyChord) -> bool {
+    chord.modifiers.is_empty() && matches!(chord.code, KeyCode::Char('1'..='9' | 'g' | 'm' | '\''))
+}
+
+/// Key bindings for every mode: the defaults with the config's `keys`
+/// section applied on top.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyMode, Vec<(KeyChord, Action)>>,
+    leader: KeyChord,
+}
+
+impl Keymap {
+    /// `leader` is the key that starts `<leader>` hotkeys; `\` when unset.
+    pub fn new(overrides: &HashMap<KeyMode, HashMap<String, Action>>, leader: Option<&str>) -> Result<Self, String> {
+        let modes = [
+            KeyMode::Normal,
+            KeyMode::Help,
+            KeyMode::Output,
+            KeyMode::Progress,
+            KeyMode::Batch,
+            KeyMode::Systemd,
+            KeyMode::Logs,
+            KeyMode::Picker,
+        ];
+        let mut bindings = HashMap::new();
+        for mode in modes {
+            let mut keys = Vec::new();
+            for (chord, action) in mode.defaults() {
+                keys.push((KeyChord::parse(chord)?, *action));
+            }
+
+            // Configured keys go first so the bars and help show them
+            let mut changes: Vec<_> = overrides.get(&mode).into_iter().flatten().collect();
+            changes.sort_by(|a, b| a.0.cmp(b.0));
+            let mut added = 0;
+            for (text, action) in changes {
+                let chord = KeyChord::parse(text)?;
+                if mode == KeyMode::Normal && is_fixed(chord) {
+                    return Err(format!("Key '{}' is used for counts, gg or marks and cannot be bound", text));
+                }
+                if *action != Action::None && !mode.actions().contains(action) {
+                    return Err(format!(
+                        "Key '{}': {} is not an action of the {} keys",
+                        text,
+                        serde_json::to_string(action).unwrap_or_default(),
+                        mode.name()
+                    ));
+                }
+                keys.retain(|(bound, _)| *bound != chord);
+                if *action != Action::None {
+                    keys.insert(added, (chord, *action));
+                    added += 1;
+                }
+            }
+            bindings.insert(mode, keys);
+        }
+
+        let text = leader.unwrap_or("\\");
+        let leader = KeyChord::parse(text)?;
+        if is_fixed(leader) || bindings[&KeyMode::Normal].iter().any(|(chord, _)| *chord == leader) {
+            return Err(format!("Leader key '{}' is already in use", text));
+        }
+        Ok(Self { bindings, leader })
+    }
+
+    pub fn leader(&self) -> KeyChord {
+        self.leader
+    }
+
+    /// Rejects a hotkey that would shadow a normal-mode key. Digits are
+    /// allowed; they then no longer start a count.
+    pub fn check_hotkey(&self, hotkey: Hotkey) -> Result<(), String> {
+        let Hotkey::Key(chord) = hotkey else {
+            return Ok(());
+        };
+        if let Some(action) = self.bindings[&KeyMode::Normal].iter().find(|(bound, _)| *bound == chord).map(|(_, a)| a) {
+            return Err(format!("'{}' is already bound to {}", chord, serde_json::to_string(action).unwrap_or_default()));
+        }
+        if chord == self.leader {
+            return Err(format!("'{}' is the leader key", chord));
+        }
+        if matches!(chord.code, KeyCode::Char('g' | 'm' | '\'')) && chord.modifiers.is_empty() {
+            return Err(format!("'{}' starts gg or a mark", chord));
+        }
+        Ok(())
+    }
+
+    /// How a hotkey is shown on the entry's badge, e.g. `1` or `\b`.
+    pub fn hotkey_label(&self, hotkey: Hotkey) -> String {
+        match hotkey {
+            Hotkey::Key(chord) => chord.to_string(),
+            Hotkey::Leader(chord) => {
+                let leader = self.leader.to_string();
+                if leader.chars().count() == 1 {
+                    format!("{}{}", leader, chord)
+                } else {
+                    format!("{} {}", leader, chord)
+                }
+            }
+        }
+    }
+
+    pub fn action(&self, mode: KeyMode, key: KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from_event(key);
+        self.bindings[&mode].iter().find(|(bound, _)| *bound == chord).map(|(_, action)| *action)
+    }
+
+    /// Every key bound to an action, defaults first.
+    pub fn chords(&self, mode: KeyMode, action: Action) -> Vec<KeyChord> {
+        self.bindings[&mode].iter().filter(|(_, bound)| *bound == action).map(|(chord, _)| *chord).collect()
+    }
+
+    /// The keys bound to an action, e.g. `Space/Esc/q`.
+    pub fn keys(&self, mode: KeyMode, action: Action) -> String {
+        self.chords(mode, action).iter().map(|chord| chord.to_string()).collect::<Vec<_>>().join("/")
+    }
+
+    /// A bottom bar like `j/k:↕ | Enter:launch`, using the first key of
+    /// each action; actions without a key are left out.
+    pub fn bar(&self, mode: KeyMode, items: &[(&[Action], &str)]) -> String {
+        items
+            .iter()
+            .filter_map(|(actions, label)| {
+                let keys: Vec<String> = actions
+                    .iter()
+                    .filter_map(|action| self.chords(mode, *action).first().map(|chord| chord.to_string()))
+                    .collect();
+                (!keys.is_empty()).then(|| format!("{}:{}", keys.join("/"), label))
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// The help screen lines for the keys of the normal mode.
+    pub fn help_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (section, actions) in NORMAL_SECTIONS {
+            lines.push(format!("{}:", section));
+            for action in actions.iter() {
+                let chords = self.chords(KeyMode::Normal, *action);
+                if chords.is_empty() {
+                    continue;
+                }
+                let keys: Vec<String> = chords.iter().map(|chord| format!("[ {} ]", chord)).collect();
+                lines.push(format!("  {:18} {}", keys.join(" "), action.describe()));
+            }
+            if *section == "NAVIGATION" {
+                // Counts, gg and marks are read before the keymap and cannot be rebound
+                lines.push("  [ 5 ] + motion     Repeat a motion, e.g. 5j (5G goes to line 5)".to_string());
+                lines.push("  [ gg ]             First program (5gg goes to line 5)".to_string());
+                lines.push("  [ ma ] [ 'a ]      Set mark a / jump to it".to_string());
+            }
+            if *section == "SELECTION" {
+                lines.push(format!(
+                    "  With programs selected, {} opens batch actions, {} deletes them and {} clears the selection",
+                    self.keys(KeyMode::Normal, Action::Launch),
+                    self.keys(KeyMode::Normal, Action::Delete),
+                    self.keys(KeyMode::Normal, Action::Quit)
+                ));
+            }
+            lines.push(String::new());
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+        KeyChord { code, modifiers }
+    }
+
+    fn overrides(mode: KeyMode, keys: &[(&str, Action)]) -> HashMap<KeyMode, HashMap<String, Action>> {
+        let keys = keys.iter().map(|(key, action)| (key.to_string(), *action)).collect();
+        HashMap::from([(mode, keys)])
+    }
+
+    #[test]
+    fn parses_modifiers_and_named_keys() {
+        assert_eq!(KeyChord::parse("q"), Ok(chord(KeyCode::Char('q'), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("Ctrl-W"), Ok(chord(KeyCode::Char('w'), KeyModifiers::CONTROL)));
+        assert_eq!(KeyChord::parse("c-w"), KeyChord::parse("Ctrl-W"));
+        assert_eq!(
+            KeyChord::parse("Ctrl-Alt-Enter"),
+            Ok(chord(KeyCode::Enter, KeyModifiers::CONTROL | KeyModifiers::ALT))
+        );
+        assert_eq!(KeyChord::parse("M-x"), Ok(chord(KeyCode::Char('x'), KeyModifiers::ALT)));
+        assert_eq!(KeyChord::parse("F12"), Ok(chord(KeyCode::F(12), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("space"), Ok(chord(KeyCode::Char(' '), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("↓"), KeyChord::parse("Down"));
+        assert_eq!(KeyChord::parse("-"), Ok(chord(KeyCode::Char('-'), KeyModifiers::NONE)));
+        assert_eq!(KeyChord::parse("Alt--"), Ok(chord(KeyCode::Char('-'), KeyModifiers::ALT)));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        assert_eq!(KeyChord::parse("Hyper-x"), Err("Invalid key 'Hyper-x': unknown modifier 'Hyper'".to_string()));
+        assert_eq!(KeyChord::parse("F13"), Err("Invalid key 'F13'".to_string()));
+        assert_eq!(KeyChord::parse("qq"), Err("Invalid key 'qq'".to_string()));
+    }
+
+    #[test]
+    fn shift_is_part_of_the_character() {
+        assert_eq!(KeyChord::parse("Shift-g"), KeyChord::parse("G"));
+        let event = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(Ok(KeyChord::from_event(event)), KeyChord::parse("G"));
+        // Shift stays on keys that are not characters
+        assert_eq!(KeyChord::parse("Shift-Tab"), Ok(chord(KeyCode::Tab, KeyModifiers::SHIFT)));
+        assert_eq!(KeyChord::parse("Shift-Tab").unwrap().to_string(), "Shift-Tab");
+        assert_eq!(KeyChord::parse("Ctrl-w").unwrap().to_string(), "Ctrl-W");
+    }
+
+    #[test]
+    fn parses_hotkeys() {
+        assert_eq!(Hotkey::parse("1"), Ok(Hotkey::Key(KeyChord::parse("1").unwrap())));
+        assert_eq!(Hotkey::parse("<Leader>b"), Ok(Hotkey::Leader(KeyChord::parse("b").unwrap())));
+        assert_eq!(Hotkey::parse("<leader>F2"), Ok(Hotkey::Leader(KeyChord::parse("F2").unwrap())));
+        assert!(Hotkey::parse("<leader>").is_err());
+    }
+
+    #[test]
+    fn overrides_replace_and_unbind_defaults() {
+        let keymap = Keymap::new(&overrides(KeyMode::Normal, &[("J", Action::Down), ("q", Action::None)]), None).unwrap();
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        assert_eq!(keymap.action(KeyMode::Normal, key('q')), None);
+        assert_eq!(keymap.action(KeyMode::Normal, key('j')), Some(Action::Down));
+        assert_eq!(keymap.keys(KeyMode::Normal, Action::Down), "J/j/↓");
+        assert_eq!(keymap.keys(KeyMode::Normal, Action::Quit), "Esc");
+        // Other modes keep their defaults
+        assert_eq!(keymap.action(KeyMode::Output, key('q')), Some(Action::Close));
+    }
+
+    #[test]
+    fn rejects_fixed_keys_and_foreign_actions() {
+        let err = Keymap::new(&overrides(KeyMode::Normal, &[("g", Action::Down)]), None).unwrap_err();
+        assert_eq!(err, "Key 'g' is used for counts, gg or marks and cannot be bound");
+        assert!(Keymap::new(&overrides(KeyMode::Normal, &[("5", Action::Down)]), None).is_err());
+        // Outside the normal mode those keys are free
+        assert!(Keymap::new(&overrides(KeyMode::Output, &[("g", Action::Down)]), None).is_ok());
+
+        let err = Keymap::new(&overrides(KeyMode::Help, &[("x", Action::Launch)]), None).unwrap_err();
+        assert_eq!(err, "Key 'x': \"launch\" is not an action of the help keys");
+    }
+
+    #[test]
+    fn leader_must_be_free() {
+        let none = HashMap::new();
+        assert_eq!(Keymap::new(&none, None).unwrap().leader(), KeyChord::parse("\\").unwrap());
+        assert_eq!(Keymap::new(&none, Some("j")).unwrap_err(), "Leader key 'j' is already in use");
+        assert_eq!(Keymap::new(&none, Some("m")).unwrap_err(), "Leader key 'm' is already in use");
+        // Unbinding a key frees it for the leader
+        let keymap = Keymap::new(&overrides(KeyMode::Normal, &[("j", Action::None)]), Some("j")).unwrap();
+        assert_eq!(keymap.hotkey_label(Hotkey::Leader(KeyChord::parse("b").unwrap())), "jb");
+    }
+
+    #[test]
+    fn hotkeys_cannot_shadow_bound_keys() {
+        let err = Keymap::new(&HashMap::new(), Some("Space")).unwrap_err();
+        assert_eq!(err, "Leader key 'Space' is already in use");
+
+        let keymap = Keymap::new(&HashMap::new(), Some("F2")).unwrap();
+        let key = |text| Hotkey::parse(text).unwrap();
+        assert_eq!(keymap.check_hotkey(key("1")), Ok(()));
+        assert_eq!(keymap.check_hotkey(key("<leader>j")), Ok(()));
+        assert_eq!(keymap.check_hotkey(key("j")), Err("'j' is already bound to \"down\"".to_string()));
+        assert_eq!(keymap.check_hotkey(key("F2")), Err("'F2' is the leader key".to_string()));
+        assert_eq!(keymap.check_hotkey(key("m")), Err("'m' starts gg or a mark".to_string()));
+        assert_eq!(keymap.hotkey_label(key("<leader>b")), "F2 b");
+    }
+}
@@ -0,0 +1,136 @@
+use crate::config::LogSource;
+use crate::theme::{Style, Theme};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+/// Lines of history shown when a tail starts.
+const BACKLOG_LINES: &str = "200";
+
+/// Lines kept in memory; older ones are dropped as new ones arrive.
+const MAX_LINES: usize = 10_000;
+
+impl LogSource {
+    /// The command that prints the recent lines of the source and then
+    /// keeps following it.
+    pub fn follow_command(&self) -> (&'static str, Vec<String>) {
+        let (command, args) = match self {
+            LogSource::Unit(unit) => ("journalctl", vec!["-u", unit, "-n", BACKLOG_LINES, "-f", "--no-pager"]),
+            LogSource::File(path) => ("tail", vec!["-n", BACKLOG_LINES, "-F", path]),
+        };
+        (command, args.into_iter().map(String::from).collect())
+    }
+}
+
+/// The lines read so far, with a count of those dropped from the front so
+/// positions stay stable while the buffer is trimmed.
+#[derive(Default)]
+pub struct Lines {
+    pub lines: VecDeque<String>,
+    pub dropped: usize,
+}
+
+impl Lines {
+    /// Total number of lines ever read.
+    pub fn total(&self) -> usize {
+        self.dropped + self.lines.len()
+    }
+
+    fn push(&mut self, line: String) {
+        if self.lines.len() == MAX_LINES {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line);
+    }
+}
+
+/// A program that follows a log, read line by line in the background.
+pub struct LogTail {
+    child: Child,
+    lines: Arc<Mutex<Lines>>,
+    finished: bool,
+}
+
+impl LogTail {
+    pub fn spawn(mut cmd: Command) -> io::Result<Self> {
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0);
+        let mut child = cmd.spawn()?;
+
+        let lines = Arc::new(Mutex::new(Lines::default()));
+        if let Some(stdout) = child.stdout.take() {
+            Self::read_lines(stdout, Arc::clone(&lines));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            Self::read_lines(stderr, Arc::clone(&lines));
+        }
+
+        Ok(Self {
+            child,
+            lines,
+            finished: false,
+        })
+    }
+
+    fn read_lines<R: Read + Send + 'static>(source: R, lines: Arc<Mutex<Lines>>) {
+        thread::spawn(move || {
+            let mut reader = BufReader::new(source);
+            let mut line = Vec::new();
+            while let Ok(n) = reader.read_until(b'\n', &mut line) {
+                if n == 0 {
+                    break;
+                }
+                let text = String::from_utf8_lossy(&line);
+                lines.lock().unwrap().push(text.trim_end_matches(['\n', '\r']).to_string());
+                line.clear();
+            }
+        });
+    }
+
+    pub fn lines(&self) -> MutexGuard<'_, Lines> {
+        self.lines.lock().unwrap()
+    }
+
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let status = self.child.try_wait()?;
+        self.finished = status.is_some();
+        Ok(status)
+    }
+}
+
+impl Drop for LogTail {
+    fn drop(&mut self) {
+        if !self.finished {
+            // SAFETY: kill(2) has no memory-safety preconditions; the child
+            // leads its own process group.
+            unsafe {
+                libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL);
+            }
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// The style for a log line, guessed from the severity words it contains.
+pub fn level_style(line: &str, theme: &Theme) -> Option<Style> {
+    let line = line.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|word| line.contains(word));
+    if has(&["emerg", "alert", "crit", "error", "fatal", "panic", "fail"]) {
+        Some(theme.error)
+    } else if has(&["warn"]) {
+        Some(theme.warning)
+    } else if has(&["debug", "trace"]) {
+        Some(theme.dim)
+    } else {
+        None
+    }
+}
@@ -1,7 +1,27 @@
 use std::io;
 
+mod ansi;
+mod clipboard;
+mod command;
 mod config;
+mod container;
+mod keymap;
+mod logs;
+mod metrics;
+mod motion;
+mod privilege;
+mod pty;
+mod render;
+mod runner;
+mod ssh;
+mod sudo;
+mod sysinfo;
+mod systemd;
+mod template;
+mod text;
+mod theme;
 mod ui;
+mod vt;
 
 use ui::Dashboard;
 
@@ -0,0 +1,227 @@
+use crate::sysinfo;
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// How often the metrics are read again.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Used and total bytes of memory, swap or a filesystem.
+#[derive(Debug, Clone, Copy)]
+pub struct Usage {
+    pub used: u64,
+    pub total: u64,
+}
+
+impl Usage {
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.used as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+/// One reading of the machine's state. Rates (CPU, network) need two
+/// readings and are missing from the first.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    /// Percent of CPU time not idle since the last reading.
+    pub cpu: Option<f64>,
+    pub load: Option<(f64, f64, f64)>,
+    pub memory: Option<Usage>,
+    pub swap: Option<Usage>,
+    /// Mount point and usage of each mounted disk.
+    pub disks: Vec<(String, Usage)>,
+    pub uptime: Option<Duration>,
+    /// Bytes received and sent per second on all interfaces but loopback.
+    pub network: Option<(f64, f64)>,
+}
+
+/// Reads the metrics from `/proc` and keeps the counters rates are worked
+/// out from.
+#[derive(Debug, Default)]
+pub struct Monitor {
+    metrics: Metrics,
+    cpu_times: Option<(u64, u64)>,         // (busy, total) jiffies
+    net_bytes: Option<(Instant, u64, u64)>, // (read at, received, sent)
+    read_at: Option<Instant>,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        let mut monitor = Self::default();
+        monitor.refresh();
+        monitor
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.read_at.is_none_or(|at| at.elapsed() >= REFRESH_INTERVAL)
+    }
+
+    pub fn refresh(&mut self) {
+        let cpu_times = cpu_times();
+        let cpu = match (self.cpu_times, cpu_times) {
+            (Some((busy, total)), Some((new_busy, new_total))) if new_total > total => {
+                Some(new_busy.saturating_sub(busy) as f64 * 100.0 / (new_total - total) as f64)
+            }
+            _ => None,
+        };
+        self.cpu_times = cpu_times;
+
+        let now = Instant::now();
+        let net_bytes = net_bytes().map(|(received, sent)| (now, received, sent));
+        let network = match (self.net_bytes, net_bytes) {
+            (Some((at, received, sent)), Some((new_at, new_received, new_sent))) => {
+                let seconds = new_at.duration_since(at).as_secs_f64().max(0.001);
+                Some((
+                    new_received.saturating_sub(received) as f64 / seconds,
+                    new_sent.saturating_sub(sent) as f64 / seconds,
+                ))
+            }
+            _ => None,
+        };
+        self.net_bytes = net_bytes;
+
+        let (memory, swap) = memory();
+        self.metrics = Metrics {
+            cpu,
+            load: sysinfo::load_average(),
+            memory,
+            swap,
+            disks: disks(),
+            uptime: sysinfo::uptime(),
+            network,
+        };
+        self.read_at = Some(now);
+    }
+}
+
+/// Sizes the way `df -h` shows them, e.g. `512B`, `1.5K` or `20G`.
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: &[&str] = &["B", "K", "M", "G", "T", "P"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 || value >= 10.0 {
+        format!("{:.0}{}", value, UNITS[unit])
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
+
+/// A mount point cut to `width` characters; long ones keep their end,
+/// which tells them apart.
+pub fn short_mount(mount: &str, width: usize) -> String {
+    let chars: Vec<char> = mount.chars().collect();
+    if chars.len() <= width {
+        return mount.to_string();
+    }
+    let tail: String = chars[chars.len() + 1 - width..].iter().collect();
+    format!("…{}", tail)
+}
+
+/// Busy and total jiffies of all CPUs from the first line of `/proc/stat`.
+fn cpu_times() -> Option<(u64, u64)> {
+    let content = fs::read_to_string("/proc/stat").ok()?;
+    let line = content.lines().next()?.strip_prefix("cpu ")?;
+    // user nice system idle iowait irq softirq steal; guest time is already
+    // counted in user
+    let times: Vec<u64> = line.split_whitespace().take(8).filter_map(|t| t.parse().ok()).collect();
+    if times.len() < 5 {
+        return None;
+    }
+    let total: u64 = times.iter().sum();
+    let idle = times[3] + times[4];
+    Some((total - idle, total))
+}
+
+fn memory() -> (Option<Usage>, Option<Usage>) {
+    let Ok(content) = fs::read_to_string("/proc/meminfo") else {
+        return (None, None);
+    };
+    let field = |name: &str| {
+        content.lines().find_map(|line| {
+            let value = line.strip_prefix(name)?.strip_prefix(':')?;
+            value.split_whitespace().next()?.parse::<u64>().ok().map(|kb| kb * 1024)
+        })
+    };
+    let usage = |total: Option<u64>, free: Option<u64>| {
+        let total = total?;
+        Some(Usage { used: total.saturating_sub(free?), total })
+    };
+    (
+        usage(field("MemTotal"), field("MemAvailable")),
+        usage(field("SwapTotal"), field("SwapFree")),
+    )
+}
+
+/// The root filesystem and everything mounted from a block device, once
+/// each.
+fn disks() -> Vec<(String, Usage)> {
+    let Ok(content) = fs::read_to_string("/proc/mounts") else {
+        return Vec::new();
+    };
+    let mut devices = Vec::new();
+    let mut disks = Vec::new();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(device), Some(mount), Some(fs_type)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let is_disk = device.starts_with("/dev/") && fs_type != "squashfs";
+        if !(is_disk || mount == "/") || devices.contains(&device) {
+            continue;
+        }
+        // Spaces and other odd characters in mount points are octal escapes
+        let mount = mount.replace("\\040", " ");
+        if let Some(usage) = filesystem_usage(&mount) {
+            devices.push(device);
+            disks.push((mount, usage));
+        }
+    }
+    disks
+}
+
+fn filesystem_usage(mount: &str) -> Option<Usage> {
+    let path = std::ffi::CString::new(mount).ok()?;
+    // SAFETY: statvfs only writes to the struct it is given; the path is a
+    // valid C string for the duration of the call
+    let stat = unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+        stat
+    };
+    let block = stat.f_frsize as u64;
+    let total = stat.f_blocks as u64 * block;
+    (total > 0).then(|| Usage { used: (stat.f_blocks - stat.f_bfree) as u64 * block, total })
+}
+
+/// Bytes received and sent on all interfaces but loopback, from
+/// `/proc/net/dev`.
+fn net_bytes() -> Option<(u64, u64)> {
+    let content = fs::read_to_string("/proc/net/dev").ok()?;
+    let mut totals = (0, 0);
+    // Two header lines, then `iface: rx_bytes packets ... tx_bytes ...`
+    for line in content.lines().skip(2) {
+        let Some((interface, counters)) = line.split_once(':') else { continue };
+        if interface.trim() == "lo" {
+            continue;
+        }
+        let counters: Vec<u64> = counters.split_whitespace().filter_map(|c| c.parse().ok()).collect();
+        if counters.len() >= 9 {
+            totals.0 += counters[0];
+            totals.1 += counters[8];
+        }
+    }
+    Some(totals)
+}
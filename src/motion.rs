@@ -0,0 +1,169 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A movement through the entry list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Motion {
+    Down(usize),
+    Up(usize),
+    /// `gg`, or `{count}gg`/`{count}G` for a 1-based line.
+    Line(usize),
+    First,
+    Last,
+    ScreenTop,
+    ScreenMiddle,
+    ScreenBottom,
+    SetMark(char),
+    JumpToMark(char),
+    JumpBack(usize),
+    JumpForward(usize),
+}
+
+impl Motion {
+    /// Motions that record the position they leave in the jump list.
+    pub fn is_jump(self) -> bool {
+        matches!(
+            self,
+            Motion::Line(_)
+                | Motion::First
+                | Motion::Last
+                | Motion::ScreenTop
+                | Motion::ScreenMiddle
+                | Motion::ScreenBottom
+                | Motion::JumpToMark(_)
+        )
+    }
+}
+
+/// What a key press means once added to the keys typed so far.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parsed {
+    /// More keys are needed.
+    Pending,
+    Motion(Motion),
+    /// The pending keys were dropped, by Esc or an invalid sequence.
+    Cleared,
+    /// A key for the keymap, with the count typed before it.
+    Key(KeyEvent, Option<usize>),
+}
+
+/// Collects count prefixes and the fixed multi-key motions (`gg`, `ma`,
+/// `'a`) for the entry list. Single keys are left to the keymap.
+#[derive(Debug, Default)]
+pub struct KeySequence {
+    count: Option<usize>,
+    prefix: Option<char>,
+}
+
+impl KeySequence {
+    /// The keys typed so far, for the pending-keys indicator.
+    pub fn pending(&self) -> String {
+        let count = self.count.map_or_else(String::new, |count| count.to_string());
+        format!("{}{}", count, self.prefix.map_or_else(String::new, String::from))
+    }
+
+    pub fn feed(&mut self, key: KeyEvent) -> Parsed {
+        let count = self.count.take();
+        let prefix = self.prefix.take();
+
+        if key.code == KeyCode::Esc && (count.is_some() || prefix.is_some()) {
+            return Parsed::Cleared;
+        }
+
+        if let Some(prefix) = prefix {
+            return match (prefix, key.code) {
+                ('g', KeyCode::Char('g')) => Parsed::Motion(count.map_or(Motion::First, Motion::Line)),
+                ('m', KeyCode::Char(c)) if c.is_ascii_lowercase() => Parsed::Motion(Motion::SetMark(c)),
+                ('\'', KeyCode::Char(c)) if c.is_ascii_lowercase() => Parsed::Motion(Motion::JumpToMark(c)),
+                _ => Parsed::Cleared,
+            };
+        }
+
+        if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return Parsed::Key(key, count);
+        }
+        match key.code {
+            KeyCode::Char(c @ '1'..='9') => {
+                self.count = Some(Self::append_digit(count, c));
+                Parsed::Pending
+            }
+            KeyCode::Char('0') if count.is_some() => {
+                self.count = Some(Self::append_digit(count, '0'));
+                Parsed::Pending
+            }
+            KeyCode::Char(c @ ('g' | 'm' | '\'')) => {
+                self.count = count;
+                self.prefix = Some(c);
+                Parsed::Pending
+            }
+            _ => Parsed::Key(key, count),
+        }
+    }
+
+    fn append_digit(count: Option<usize>, digit: char) -> usize {
+        let digit = digit.to_digit(10).unwrap_or(0) as usize;
+        count.unwrap_or(0).saturating_mul(10).saturating_add(digit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn feed(sequence: &mut KeySequence, keys: &str) -> Vec<Parsed> {
+        keys.chars().map(|c| sequence.feed(key(c))).collect()
+    }
+
+    #[test]
+    fn counts_prefix_the_next_key() {
+        let mut sequence = KeySequence::default();
+        assert_eq!(feed(&mut sequence, "12"), [Parsed::Pending, Parsed::Pending]);
+        assert_eq!(sequence.pending(), "12");
+        assert_eq!(sequence.feed(key('j')), Parsed::Key(key('j'), Some(12)));
+        assert_eq!(sequence.pending(), "");
+        // A leading zero is a key of its own, a later one part of the count
+        assert_eq!(sequence.feed(key('0')), Parsed::Key(key('0'), None));
+        assert_eq!(feed(&mut sequence, "10j")[2], Parsed::Key(key('j'), Some(10)));
+    }
+
+    #[test]
+    fn huge_counts_saturate() {
+        let mut sequence = KeySequence::default();
+        feed(&mut sequence, "99999999999999999999999");
+        assert_eq!(sequence.feed(key('G')), Parsed::Key(key('G'), Some(usize::MAX)));
+    }
+
+    #[test]
+    fn reads_gg_and_marks() {
+        let mut sequence = KeySequence::default();
+        assert_eq!(feed(&mut sequence, "gg")[1], Parsed::Motion(Motion::First));
+        assert_eq!(feed(&mut sequence, "5gg")[2], Parsed::Motion(Motion::Line(5)));
+        assert_eq!(feed(&mut sequence, "ma")[1], Parsed::Motion(Motion::SetMark('a')));
+        assert_eq!(feed(&mut sequence, "'z")[1], Parsed::Motion(Motion::JumpToMark('z')));
+        assert_eq!(sequence.pending(), "");
+    }
+
+    #[test]
+    fn invalid_sequences_and_esc_clear_pending_keys() {
+        let mut sequence = KeySequence::default();
+        assert_eq!(feed(&mut sequence, "gj")[1], Parsed::Cleared);
+        assert_eq!(feed(&mut sequence, "mA")[1], Parsed::Cleared);
+        feed(&mut sequence, "3g");
+        assert_eq!(sequence.pending(), "3g");
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(sequence.feed(esc), Parsed::Cleared);
+        // Without pending keys Esc goes to the keymap
+        assert_eq!(sequence.feed(esc), Parsed::Key(esc, None));
+    }
+
+    #[test]
+    fn modified_keys_go_to_the_keymap() {
+        let mut sequence = KeySequence::default();
+        let ctrl_g = KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL);
+        feed(&mut sequence, "4");
+        assert_eq!(sequence.feed(ctrl_g), Parsed::Key(ctrl_g, Some(4)));
+    }
+}
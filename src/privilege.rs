@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+/// The program used to run commands as another user.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Sudo,
+    Doas,
+    Pkexec,
+    #[serde(rename = "systemd-run")]
+    SystemdRun,
+    /// systemd's sudo replacement, a front end to systemd-run.
+    Run0,
+}
+
+impl Backend {
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Sudo => "sudo",
+            Backend::Doas => "doas",
+            Backend::Pkexec => "pkexec",
+            Backend::SystemdRun => "systemd-run",
+            Backend::Run0 => "run0",
+        }
+    }
+
+    pub fn supports_group(self) -> bool {
+        matches!(self, Backend::Sudo | Backend::SystemdRun | Backend::Run0)
+    }
+}
+
+/// How a command gains privileges: the backend and the account to run as,
+/// `user`, `user:group` or `:group`. Without an account it runs as root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Elevation {
+    pub backend: Backend,
+    pub run_as: Option<String>,
+}
+
+impl Elevation {
+    fn user(&self) -> Option<&str> {
+        let run_as = self.run_as.as_deref()?;
+        let user = run_as.split_once(':').map_or(run_as, |(user, _)| user);
+        (!user.is_empty()).then_some(user)
+    }
+
+    fn group(&self) -> Option<&str> {
+        let (_, group) = self.run_as.as_deref()?.split_once(':')?;
+        (!group.is_empty()).then_some(group)
+    }
+
+    /// The words that go before the command. Captured runs have no terminal
+    /// to ask for a password on, so they tell the backend not to ask.
+    pub fn prefix(&self, interactive: bool) -> Vec<String> {
+        let mut words = vec![self.backend.name().to_string()];
+        match self.backend {
+            Backend::Sudo => {
+                if !interactive {
+                    words.push("-n".to_string());
+                }
+                if let Some(user) = self.user() {
+                    words.extend(["-u".to_string(), user.to_string()]);
+                }
+                if let Some(group) = self.group() {
+                    words.extend(["-g".to_string(), group.to_string()]);
+                }
+            }
+            Backend::Doas => {
+                if !interactive {
+                    words.push("-n".to_string());
+                }
+                if let Some(user) = self.user() {
+                    words.extend(["-u".to_string(), user.to_string()]);
+                }
+            }
+            Backend::Pkexec => {
+                // pkexec has no flag against prompting; without its text
+                // agent a captured run fails when no polkit agent is running
+                if !interactive {
+                    words.push("--disable-internal-agent".to_string());
+                }
+                if let Some(user) = self.user() {
+                    words.extend(["--user".to_string(), user.to_string()]);
+                }
+            }
+            Backend::SystemdRun => {
+                words.extend(["--quiet", "--wait", "--collect"].map(String::from));
+                words.push(if interactive { "--pty" } else { "--pipe" }.to_string());
+                if !interactive {
+                    words.push("--no-ask-password".to_string());
+                }
+                if let Some(user) = self.user() {
+                    words.push(format!("--uid={}", user));
+                }
+                if let Some(group) = self.group() {
+                    words.push(format!("--gid={}", group));
+                }
+                words.push("--".to_string());
+            }
+            Backend::Run0 => {
+                // run0 picks a terminal or pipes by itself
+                if !interactive {
+                    words.push("--no-ask-password".to_string());
+                }
+                if let Some(user) = self.user() {
+                    words.push(format!("--user={}", user));
+                }
+                if let Some(group) = self.group() {
+                    words.push(format!("--group={}", group));
+                }
+                words.push("--".to_string());
+            }
+        }
+        words
+    }
+
+    /// Short form for the entry list, e.g. `SUDO` or `DOAS as postgres`.
+    pub fn label(&self) -> String {
+        let backend = self.backend.name().to_uppercase();
+        match &self.run_as {
+            Some(run_as) => format!("{} as {}", backend, run_as),
+            None => backend,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefix(backend: Backend, run_as: Option<&str>, interactive: bool) -> String {
+        let elevation = Elevation { backend, run_as: run_as.map(String::from) };
+        elevation.prefix(interactive).join(" ")
+    }
+
+    #[test]
+    fn captured_runs_never_prompt() {
+        assert_eq!(prefix(Backend::Sudo, None, false), "sudo -n");
+        assert_eq!(prefix(Backend::Doas, Some("www"), false), "doas -n -u www");
+        assert_eq!(prefix(Backend::Pkexec, Some("www"), false), "pkexec --disable-internal-agent --user www");
+        assert_eq!(
+            prefix(Backend::SystemdRun, Some("www:adm"), false),
+            "systemd-run --quiet --wait --collect --pipe --no-ask-password --uid=www --gid=adm --"
+        );
+        assert_eq!(prefix(Backend::Run0, Some(":adm"), false), "run0 --no-ask-password --group=adm --");
+    }
+
+    #[test]
+    fn interactive_runs_may_prompt() {
+        assert_eq!(prefix(Backend::Sudo, Some("www:adm"), true), "sudo -u www -g adm");
+        assert_eq!(prefix(Backend::Pkexec, None, true), "pkexec");
+        assert_eq!(prefix(Backend::SystemdRun, None, true), "systemd-run --quiet --wait --collect --pty --");
+        assert_eq!(prefix(Backend::Run0, Some("www"), true), "run0 --user=www --");
+    }
+
+    #[test]
+    fn backends_keep_their_config_names() {
+        for backend in [Backend::Sudo, Backend::Doas, Backend::Pkexec, Backend::SystemdRun, Backend::Run0] {
+            let json = serde_json::to_string(&backend).unwrap();
+            assert_eq!(json, format!("\"{}\"", backend.name()));
+            assert_eq!(serde_json::from_str::<Backend>(&json).unwrap(), backend);
+        }
+    }
+}
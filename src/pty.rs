@@ -0,0 +1,223 @@
+use crate::vt::Screen;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+/// A program running on a pseudo-terminal whose output is fed into a
+/// `Screen` so the dashboard can render it in a pane.
+pub struct PtySession {
+    child: Child,
+    master: File,
+    screen: Arc<Mutex<Screen>>,
+    updated: Arc<AtomicBool>, // output arrived since the last `take_updated`
+    finished: bool,
+}
+
+impl PtySession {
+    pub fn spawn(mut cmd: Command, rows: u16, cols: u16) -> io::Result<Self> {
+        let (master, slave) = open_pty(rows, cols)?;
+
+        cmd.stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave))
+            .env("TERM", "xterm-256color");
+        // SAFETY: only async-signal-safe calls between fork and exec
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = cmd.spawn()?;
+
+        let screen = Arc::new(Mutex::new(Screen::new(rows as usize, cols as usize)));
+        let mut reader = master.try_clone()?;
+        let mut replies = master.try_clone()?;
+        let output = Arc::clone(&screen);
+        let updated = Arc::new(AtomicBool::new(true));
+        let output_updated = Arc::clone(&updated);
+        thread::spawn(move || {
+            let mut chunk = [0u8; 4096];
+            // Reading fails with EIO once every slave descriptor is closed
+            while let Ok(n) = reader.read(&mut chunk) {
+                if n == 0 {
+                    break;
+                }
+                let reply = {
+                    let mut screen = output.lock().unwrap();
+                    screen.process(&chunk[..n]);
+                    screen.take_replies()
+                };
+                output_updated.store(true, Ordering::Relaxed);
+                if !reply.is_empty() && replies.write_all(&reply).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            master,
+            screen,
+            updated,
+            finished: false,
+        })
+    }
+
+    pub fn screen(&self) -> MutexGuard<'_, Screen> {
+        self.screen.lock().unwrap()
+    }
+
+    /// Whether the program has written anything since this was last asked.
+    pub fn take_updated(&self) -> bool {
+        self.updated.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn send_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let bytes = key_to_bytes(key, self.screen().app_cursor_keys());
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        self.master.write_all(&bytes)
+    }
+
+    /// Resizes the terminal; the kernel delivers SIGWINCH to the program.
+    pub fn resize(&mut self, rows: u16, cols: u16) -> io::Result<()> {
+        if self.screen().size() == (rows as usize, cols as usize) {
+            return Ok(());
+        }
+        set_window_size(&self.master, rows, cols)?;
+        self.screen().resize(rows as usize, cols as usize);
+        Ok(())
+    }
+
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        let status = self.child.try_wait()?;
+        self.finished = status.is_some();
+        Ok(status)
+    }
+}
+
+impl Drop for PtySession {
+    fn drop(&mut self) {
+        if !self.finished {
+            // SAFETY: kill(2) has no memory-safety preconditions; the child
+            // leads its own session, so its pid is also its process group.
+            unsafe {
+                libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL);
+            }
+            let _ = self.child.wait();
+        }
+    }
+}
+
+fn open_pty(rows: u16, cols: u16) -> io::Result<(File, File)> {
+    let mut master = -1;
+    let mut slave = -1;
+    let size = window_size(rows, cols);
+    // SAFETY: openpty writes two descriptors into the provided integers and
+    // reads the window size; the name and termios pointers may be null.
+    let result = unsafe {
+        libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size)
+    };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: both descriptors were just opened and are owned by nobody else
+    let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+
+    // Keep the master out of the child; only the slave becomes its terminal
+    // SAFETY: fcntl on a valid descriptor
+    unsafe {
+        libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC);
+    }
+
+    Ok((File::from(master), File::from(slave)))
+}
+
+fn window_size(rows: u16, cols: u16) -> libc::winsize {
+    libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+fn set_window_size(master: &File, rows: u16, cols: u16) -> io::Result<()> {
+    let size = window_size(rows, cols);
+    // SAFETY: TIOCSWINSZ reads a winsize struct from the pointer
+    if unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Encodes a key press the way an xterm would send it to the program.
+fn key_to_bytes(key: KeyEvent, app_cursor_keys: bool) -> Vec<u8> {
+    let cursor = |c: char| {
+        if app_cursor_keys {
+            format!("\x1bO{}", c).into_bytes()
+        } else {
+            format!("\x1b[{}", c).into_bytes()
+        }
+    };
+
+    let mut bytes = match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => match c {
+            'a'..='z' => vec![c as u8 - b'a' + 1],
+            '@' | ' ' => vec![0],
+            '[' => vec![0x1b],
+            '\\' => vec![0x1c],
+            ']' => vec![0x1d],
+            '^' => vec![0x1e],
+            '_' => vec![0x1f],
+            _ => c.to_string().into_bytes(),
+        },
+        KeyCode::Char(c) => c.to_string().into_bytes(),
+        KeyCode::Enter => vec![b'\r'],
+        KeyCode::Tab => vec![b'\t'],
+        KeyCode::BackTab => b"\x1b[Z".to_vec(),
+        KeyCode::Backspace => vec![0x7f],
+        KeyCode::Esc => vec![0x1b],
+        KeyCode::Up => cursor('A'),
+        KeyCode::Down => cursor('B'),
+        KeyCode::Right => cursor('C'),
+        KeyCode::Left => cursor('D'),
+        KeyCode::Home => cursor('H'),
+        KeyCode::End => cursor('F'),
+        KeyCode::Insert => b"\x1b[2~".to_vec(),
+        KeyCode::Delete => b"\x1b[3~".to_vec(),
+        KeyCode::PageUp => b"\x1b[5~".to_vec(),
+        KeyCode::PageDown => b"\x1b[6~".to_vec(),
+        KeyCode::F(n) => match n {
+            1 => b"\x1bOP".to_vec(),
+            2 => b"\x1bOQ".to_vec(),
+            3 => b"\x1bOR".to_vec(),
+            4 => b"\x1bOS".to_vec(),
+            5 => b"\x1b[15~".to_vec(),
+            6 => b"\x1b[17~".to_vec(),
+            7 => b"\x1b[18~".to_vec(),
+            8 => b"\x1b[19~".to_vec(),
+            9 => b"\x1b[20~".to_vec(),
+            10 => b"\x1b[21~".to_vec(),
+            11 => b"\x1b[23~".to_vec(),
+            12 => b"\x1b[24~".to_vec(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+
+    if key.modifiers.contains(KeyModifiers::ALT) && !bytes.is_empty() {
+        bytes.insert(0, 0x1b);
+    }
+    bytes
+}
@@ -0,0 +1,74 @@
+use crate::vt::{Cell, Screen, WIDE_TAIL};
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{Attribute, Print, ResetColor, SetAttribute},
+    terminal::{Clear, ClearType},
+};
+use std::io::{self, Write};
+
+/// Puts frames on the terminal. Each frame is drawn into a [`Screen`] first
+/// and compared with the one before, so only the cells that changed are
+/// sent, all in one write.
+#[derive(Default)]
+pub struct Renderer {
+    shown: Option<Screen>, // what the terminal shows now
+}
+
+impl Renderer {
+    /// An empty frame the size of the terminal to draw into.
+    pub fn frame((width, height): (u16, u16)) -> Screen {
+        Screen::new(height as usize, width as usize)
+    }
+
+    /// Forgets what the terminal shows, so the next frame is drawn whole;
+    /// for when something else has drawn on it.
+    pub fn invalidate(&mut self) {
+        self.shown = None;
+    }
+
+    /// The text the terminal shows on a row, one character per column;
+    /// the right half of a wide character is [`WIDE_TAIL`].
+    pub fn shown_row(&self, row: usize) -> Option<String> {
+        let shown = self.shown.as_ref()?;
+        (row < shown.size().0).then(|| shown.row(row).iter().map(|cell| cell.ch).collect())
+    }
+
+    pub fn render(&mut self, frame: Screen) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        let shown = self.shown.take().filter(|shown| shown.size() == frame.size());
+        if shown.is_none() {
+            queue!(stdout, SetAttribute(Attribute::Reset), ResetColor, Clear(ClearType::All))?;
+        }
+
+        let (rows, cols) = frame.size();
+        let mut cursor = None;
+        let mut style = None;
+        for row in 0..rows {
+            let before = shown.as_ref().map(|shown| shown.row(row));
+            for (col, cell) in frame.row(row).iter().enumerate() {
+                // A cleared terminal already shows blank cells
+                let unchanged = before.map_or(*cell == Cell::default(), |before| before[col] == *cell);
+                // The right half of a wide character is drawn with its left
+                if unchanged || cell.ch == WIDE_TAIL {
+                    continue;
+                }
+                if cursor != Some((row, col)) {
+                    queue!(stdout, MoveTo(col as u16, row as u16))?;
+                }
+                if style != Some(cell.style) {
+                    queue!(stdout, cell.style)?;
+                    style = Some(cell.style);
+                }
+                queue!(stdout, Print(cell))?;
+                let next = col + if cell.is_wide() { 2 } else { 1 };
+                cursor = Some((row, next)).filter(|_| next < cols);
+            }
+        }
+        queue!(stdout, SetAttribute(Attribute::Reset), ResetColor)?;
+        stdout.flush()?;
+
+        self.shown = Some(frame);
+        Ok(())
+    }
+}
@@ -0,0 +1,799 @@
+use std::io::{self, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long a job gets to exit after SIGTERM before it is sent SIGKILL.
+const KILL_GRACE: Duration = Duration::from_secs(3);
+
+/// How long to wait for the output readers once the process has exited.
+/// Grandchildren that keep the pipes open must not hang the dashboard.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobEnd {
+    Exited(ExitStatus),
+    Killed,
+    TimedOut,
+}
+
+#[derive(Debug)]
+pub struct JobResult {
+    pub end: JobEnd,
+    pub stdout: String,
+    pub stderr: String,
+    pub elapsed: Duration,
+}
+
+impl JobResult {
+    pub fn success(&self) -> bool {
+        matches!(self.end, JobEnd::Exited(status) if status.success())
+    }
+
+    pub fn combined_output(&self) -> String {
+        if self.stderr.is_empty() {
+            self.stdout.clone()
+        } else if self.stdout.is_empty() {
+            self.stderr.clone()
+        } else {
+            format!("STDOUT:\n{}\n\nSTDERR:\n{}", self.stdout, self.stderr)
+        }
+    }
+}
+
+struct Capture {
+    buffer: Arc<Mutex<Vec<u8>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Capture {
+    fn start<R: Read + Send + 'static>(source: Option<R>) -> Self {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let handle = source.map(|mut source| {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || {
+                let mut chunk = [0u8; 4096];
+                while let Ok(n) = source.read(&mut chunk) {
+                    if n == 0 {
+                        break;
+                    }
+                    buffer.lock().unwrap().extend_from_slice(&chunk[..n]);
+                }
+            })
+        });
+        Self { buffer, handle }
+    }
+
+    fn is_drained(&self) -> bool {
+        self.handle.as_ref().is_none_or(|h| h.is_finished())
+    }
+
+    fn take(&mut self) -> String {
+        if self.is_drained() {
+            if let Some(handle) = self.handle.take() {
+                let _ = handle.join();
+            }
+        }
+        let bytes = std::mem::take(&mut *self.buffer.lock().unwrap());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+/// A captured program running in the background. The process is placed in
+/// its own process group so cancellation also reaches anything it spawned.
+pub struct Job {
+    child: Child,
+    started: Instant,
+    timeout: Option<Duration>,
+    terminating: Option<(Instant, JobEnd)>,
+    finished: bool,
+    stdout: Capture,
+    stderr: Capture,
+}
+
+impl Job {
+    pub fn spawn(mut cmd: Command, timeout: Option<Duration>) -> io::Result<Self> {
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0);
+
+        let mut child = cmd.spawn()?;
+        let stdout = Capture::start(child.stdout.take());
+        let stderr = Capture::start(child.stderr.take());
+
+        Ok(Self {
+            child,
+            started: Instant::now(),
+            timeout,
+            terminating: None,
+            finished: false,
+            stdout,
+            stderr,
+        })
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Checks on the process without blocking, escalating a pending
+    /// termination or enforcing the timeout. Returns the result once the
+    /// process has exited.
+    pub fn poll(&mut self) -> io::Result<Option<JobResult>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        if let Some(status) = self.child.try_wait()? {
+            self.finished = true;
+            return Ok(Some(self.collect(status)));
+        }
+
+        match self.terminating {
+            Some((since, _)) => {
+                if since.elapsed() >= KILL_GRACE {
+                    self.signal(libc::SIGKILL);
+                }
+            }
+            None => {
+                if self.timeout.is_some_and(|t| self.elapsed() >= t) {
+                    self.terminate(JobEnd::TimedOut);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Asks the job to stop: SIGTERM now, SIGKILL after the grace period.
+    pub fn cancel(&mut self) {
+        if self.terminating.is_none() {
+            self.terminate(JobEnd::Killed);
+        }
+    }
+
+    fn terminate(&mut self, reason: JobEnd) {
+        self.signal(libc::SIGTERM);
+        // A job stopped by SIGTTIN/SIGTSTP only sees SIGTERM once resumed
+        self.signal(libc::SIGCONT);
+        self.terminating = Some((Instant::now(), reason));
+    }
+
+    fn signal(&self, signal: libc::c_int) {
+        let pgid = self.child.id() as libc::pid_t;
+        // SAFETY: kill(2) has no memory-safety preconditions; a negative pid
+        // addresses the process group created in `spawn`.
+        unsafe {
+            libc::kill(-pgid, signal);
+        }
+    }
+
+    fn collect(&mut self, status: ExitStatus) -> JobResult {
+        let deadline = Instant::now() + DRAIN_TIMEOUT;
+        while !(self.stdout.is_drained() && self.stderr.is_drained()) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        JobResult {
+            end: self.terminating.map_or(JobEnd::Exited(status), |(_, reason)| reason),
+            stdout: self.stdout.take(),
+            stderr: self.stderr.take(),
+            elapsed: self.elapsed(),
+        }
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.signal(libc::SIGKILL);
+            let _ = self.child.wait();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepState {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Killed,
+    TimedOut,
+    Skipped,
+    /// A health check failed, so the steps it guards will run.
+    NotReady,
+    /// Not run because its health check passed.
+    Satisfied,
+}
+
+impl StepState {
+    pub fn is_failure(self) -> bool {
+        matches!(self, Self::Failed | Self::Killed | Self::TimedOut)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Succeeded,
+    Failed,
+    Killed,
+    TimedOut,
+}
+
+pub struct WorkflowStep {
+    /// Name of the entry this step belongs to.
+    pub owner: String,
+    pub label: String,
+    pub state: StepState,
+    command: Option<Command>,
+    timeout: Option<Duration>,
+    stop_on_failure: bool,
+    /// For a health check, how many of the following steps it guards.
+    guards: Option<usize>,
+}
+
+impl WorkflowStep {
+    pub fn new(owner: String, label: String, command: Command, timeout: Option<Duration>) -> Self {
+        Self {
+            owner,
+            label,
+            state: StepState::Pending,
+            command: Some(command),
+            timeout,
+            stop_on_failure: true,
+            guards: None,
+        }
+    }
+
+    /// A health check: when it succeeds the next `guards` steps are not run.
+    pub fn check(owner: String, label: String, command: Command, timeout: Option<Duration>, guards: usize) -> Self {
+        Self {
+            guards: Some(guards),
+            ..Self::new(owner, label, command, timeout)
+        }
+    }
+
+    pub fn continue_on_failure(mut self) -> Self {
+        self.stop_on_failure = false;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct WorkflowResult {
+    pub outcome: Outcome,
+    pub output: String,
+    pub elapsed: Duration,
+}
+
+/// Captured commands run one after another in the background. A single
+/// command is simply a workflow with one step.
+pub struct Workflow {
+    steps: Vec<WorkflowStep>,
+    current: Option<(usize, Job)>,
+    cancelled: bool,
+    finished: bool,
+    started: Instant,
+    output: String,
+}
+
+impl Workflow {
+    /// Starts the first step. Failing to launch it is reported to the
+    /// caller; later launch failures become failed steps.
+    pub fn start(steps: Vec<WorkflowStep>) -> io::Result<Self> {
+        let mut workflow = Self {
+            steps,
+            current: None,
+            cancelled: false,
+            finished: false,
+            started: Instant::now(),
+            output: String::new(),
+        };
+        if !workflow.steps.is_empty() {
+            workflow.spawn_step(0)?;
+        }
+        Ok(workflow)
+    }
+
+    pub fn steps(&self) -> &[WorkflowStep] {
+        &self.steps
+    }
+
+    /// Index of the running step and how long it has been running.
+    pub fn current_step(&self) -> Option<(usize, Duration)> {
+        self.current.as_ref().map(|(i, job)| (*i, job.elapsed()))
+    }
+
+    /// The first step that failed, was killed or timed out.
+    pub fn first_failure(&self) -> Option<&WorkflowStep> {
+        self.steps.iter().find(|s| s.state.is_failure())
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Stops the running step and skips the remaining ones.
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
+        if let Some((_, job)) = self.current.as_mut() {
+            job.cancel();
+        }
+    }
+
+    pub fn poll(&mut self) -> io::Result<Option<WorkflowResult>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        if let Some((index, job)) = self.current.as_mut() {
+            let index = *index;
+            match job.poll()? {
+                Some(result) => {
+                    self.current = None;
+                    let next = self.record(index, &result);
+                    self.advance(next);
+                }
+                None => return Ok(None),
+            }
+        }
+
+        if self.current.is_some() {
+            return Ok(None);
+        }
+
+        self.finished = true;
+        Ok(Some(WorkflowResult {
+            outcome: self.outcome(),
+            output: std::mem::take(&mut self.output),
+            elapsed: self.elapsed(),
+        }))
+    }
+
+    fn spawn_step(&mut self, index: usize) -> io::Result<()> {
+        let step = &mut self.steps[index];
+        let command = step.command.take().expect("workflow step started twice");
+        let job = Job::spawn(command, step.timeout)?;
+        step.state = StepState::Running;
+        self.current = Some((index, job));
+        Ok(())
+    }
+
+    /// Starts the next step that should run, marking the rest as skipped
+    /// once the workflow has been cancelled or a step that stops it failed.
+    fn advance(&mut self, mut index: usize) {
+        while index < self.steps.len() {
+            let stopped = self.steps[..index].iter().any(|s| s.stop_on_failure && s.state.is_failure());
+            if self.cancelled || stopped {
+                for step in &mut self.steps[index..] {
+                    step.state = StepState::Skipped;
+                }
+                return;
+            }
+
+            match self.spawn_step(index) {
+                Ok(()) => return,
+                Err(e) => {
+                    self.steps[index].state = StepState::Failed;
+                    let header = self.step_header(index);
+                    self.output.push_str(&format!("{}\nError launching: {}\n\n", header, e));
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    /// Records a finished step and returns the index of the next step.
+    fn record(&mut self, index: usize, result: &JobResult) -> usize {
+        let guards = self.steps[index].guards;
+        self.steps[index].state = match result.end {
+            JobEnd::Exited(_) if result.success() => StepState::Succeeded,
+            JobEnd::Exited(_) if guards.is_some() => StepState::NotReady,
+            JobEnd::Exited(_) => StepState::Failed,
+            JobEnd::Killed => StepState::Killed,
+            JobEnd::TimedOut => StepState::TimedOut,
+        };
+
+        if self.steps.len() == 1 {
+            self.output = result.combined_output();
+        } else {
+            let header = self.step_header(index);
+            self.output.push_str(&format!(
+                "{} ({}s)\n{}\n",
+                header,
+                result.elapsed.as_secs(),
+                result.combined_output()
+            ));
+        }
+
+        match guards {
+            Some(count) if self.steps[index].state == StepState::Succeeded => {
+                let end = (index + 1 + count).min(self.steps.len());
+                for step in &mut self.steps[index + 1..end] {
+                    step.state = StepState::Satisfied;
+                }
+                end
+            }
+            _ => index + 1,
+        }
+    }
+
+    fn step_header(&self, index: usize) -> String {
+        let step = &self.steps[index];
+        let state = match step.state {
+            StepState::Succeeded => "ok",
+            StepState::Failed => "failed",
+            StepState::Killed => "killed",
+            StepState::TimedOut => "timed out",
+            StepState::NotReady => "not ready",
+            _ => "",
+        };
+        format!("==> [{}/{}] {}: {}", index + 1, self.steps.len(), step.label, state)
+    }
+
+    fn outcome(&self) -> Outcome {
+        outcome_of(self.steps.iter().map(|s| s.state), self.cancelled)
+    }
+}
+
+fn outcome_of(states: impl Iterator<Item = StepState> + Clone, cancelled: bool) -> Outcome {
+    let any = |state: StepState| states.clone().any(|s| s == state);
+    if cancelled || any(StepState::Killed) {
+        Outcome::Killed
+    } else if any(StepState::TimedOut) {
+        Outcome::TimedOut
+    } else if states.clone().any(StepState::is_failure) {
+        Outcome::Failed
+    } else {
+        Outcome::Succeeded
+    }
+}
+
+pub struct BatchItem {
+    pub name: String,
+    /// Extra information shown next to the name, like a host's address.
+    pub detail: String,
+    pub state: StepState,
+    pub output: String,
+    pub elapsed: Option<Duration>,
+    command: Option<Command>,
+    timeout: Option<Duration>,
+}
+
+impl BatchItem {
+    pub fn new(name: String, detail: String, command: Command, timeout: Option<Duration>) -> Self {
+        Self {
+            name,
+            detail,
+            state: StepState::Pending,
+            output: String::new(),
+            elapsed: None,
+            command: Some(command),
+            timeout,
+        }
+    }
+}
+
+/// Captured commands run side by side, at most `limit` at a time, each
+/// keeping its own output.
+pub struct Batch {
+    items: Vec<BatchItem>,
+    running: Vec<(usize, Job)>,
+    limit: usize,
+    cancelled: bool,
+    finished: bool,
+    started: Instant,
+}
+
+impl Batch {
+    pub fn start(items: Vec<BatchItem>, limit: usize) -> Self {
+        let mut batch = Self {
+            items,
+            running: Vec::new(),
+            limit: limit.max(1),
+            cancelled: false,
+            finished: false,
+            started: Instant::now(),
+        };
+        batch.fill();
+        batch
+    }
+
+    pub fn items(&self) -> &[BatchItem] {
+        &self.items
+    }
+
+    /// How long each running item has been running, by index.
+    pub fn running_elapsed(&self, index: usize) -> Option<Duration> {
+        self.running.iter().find(|(i, _)| *i == index).map(|(_, job)| job.elapsed())
+    }
+
+    pub fn completed(&self) -> usize {
+        self.items.iter().filter(|item| !matches!(item.state, StepState::Pending | StepState::Running)).count()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.cancelled && !self.finished
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Stops the running items and skips those not started yet.
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
+        for (_, job) in &mut self.running {
+            job.cancel();
+        }
+        for item in &mut self.items {
+            if item.state == StepState::Pending {
+                item.state = StepState::Skipped;
+            }
+        }
+    }
+
+    /// Collects finished items and starts waiting ones. Returns the overall
+    /// outcome once, when the last item has finished.
+    pub fn poll(&mut self) -> io::Result<Option<Outcome>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let mut i = 0;
+        while i < self.running.len() {
+            match self.running[i].1.poll()? {
+                Some(result) => {
+                    let (index, _) = self.running.remove(i);
+                    let item = &mut self.items[index];
+                    item.state = match result.end {
+                        JobEnd::Exited(_) if result.success() => StepState::Succeeded,
+                        JobEnd::Exited(_) => StepState::Failed,
+                        JobEnd::Killed => StepState::Killed,
+                        JobEnd::TimedOut => StepState::TimedOut,
+                    };
+                    item.output = result.combined_output();
+                    item.elapsed = Some(result.elapsed);
+                }
+                None => i += 1,
+            }
+        }
+
+        self.fill();
+        if !self.running.is_empty() {
+            return Ok(None);
+        }
+
+        self.finished = true;
+        Ok(Some(outcome_of(self.items.iter().map(|item| item.state), self.cancelled)))
+    }
+
+    fn fill(&mut self) {
+        for index in 0..self.items.len() {
+            if self.cancelled || self.running.len() >= self.limit {
+                return;
+            }
+            let item = &mut self.items[index];
+            if item.state != StepState::Pending {
+                continue;
+            }
+
+            let command = item.command.take().expect("batch item started twice");
+            match Job::spawn(command, item.timeout) {
+                Ok(job) => {
+                    item.state = StepState::Running;
+                    self.running.push((index, job));
+                }
+                Err(e) => {
+                    item.state = StepState::Failed;
+                    item.output = format!("Error launching: {}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::{self, SshHost};
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    /// A directory holding an `ssh` stand-in that echoes the host it was
+    /// given and records how many copies were running when it started.
+    fn stub_ssh(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rdash-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("running")).unwrap();
+        let script = dir.join("ssh");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\n\
+                 dir='{}'\n\
+                 touch \"$dir/running/$$\"\n\
+                 ls \"$dir/running\" | wc -l >> \"$dir/counts\"\n\
+                 sleep 0.2\n\
+                 rm \"$dir/running/$$\"\n\
+                 while [ \"$1\" != -- ]; do host=$1; shift; done\n\
+                 echo \"$host: $2\"\n\
+                 [ \"$host\" != broken ]\n",
+                dir.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        dir
+    }
+
+    fn host_item(stub: &Path, alias: &str) -> BatchItem {
+        let host = SshHost { alias: alias.to_string(), hostname: None, user: None, port: None };
+        let mut cmd = ssh::command(&host, "uptime", false);
+        let path = std::env::var("PATH").unwrap_or_default();
+        cmd.env("PATH", format!("{}:{}", stub.display(), path));
+        BatchItem::new(alias.to_string(), host.address(), cmd, None)
+    }
+
+    fn run(batch: &mut Batch) -> Outcome {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Some(outcome) = batch.poll().unwrap() {
+                return outcome;
+            }
+            assert!(Instant::now() < deadline, "batch did not finish");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn finish(job: &mut Job) -> JobResult {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Some(result) = job.poll().unwrap() {
+                return result;
+            }
+            assert!(Instant::now() < deadline, "job did not finish");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Whether anything is left in the process group, polling for a while
+    /// since the SIGKILL sent to the group is delivered asynchronously.
+    fn group_is_gone(pgid: u32) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            // SAFETY: signal 0 only checks that the group exists.
+            if unsafe { libc::kill(-(pgid as libc::pid_t), 0) } != 0 {
+                return io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH);
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn timed_out_job_ignoring_sigterm_is_killed_after_the_grace_period() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "trap '' TERM; sleep 10"]);
+        let mut job = Job::spawn(cmd, Some(Duration::from_millis(200))).unwrap();
+        let pgid = job.child.id();
+
+        let result = finish(&mut job);
+        assert_eq!(result.end, JobEnd::TimedOut);
+        assert!(!result.success());
+        assert!(result.elapsed >= KILL_GRACE, "{:?}", result.elapsed);
+        assert!(group_is_gone(pgid));
+    }
+
+    #[test]
+    fn cancelled_job_stops_on_sigterm() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 10; true"]);
+        let mut job = Job::spawn(cmd, None).unwrap();
+        let pgid = job.child.id();
+        assert!(job.poll().unwrap().is_none());
+
+        job.cancel();
+        let result = finish(&mut job);
+        assert_eq!(result.end, JobEnd::Killed);
+        assert!(result.elapsed < KILL_GRACE, "{:?}", result.elapsed);
+        assert!(group_is_gone(pgid));
+    }
+
+    fn step(program: &str) -> WorkflowStep {
+        WorkflowStep::new("app".to_string(), program.to_string(), Command::new(program), None)
+    }
+
+    fn check(program: &str, guards: usize) -> WorkflowStep {
+        WorkflowStep::check("app".to_string(), program.to_string(), Command::new(program), None, guards)
+    }
+
+    fn run_workflow(steps: Vec<WorkflowStep>) -> (Outcome, Vec<StepState>) {
+        let mut workflow = Workflow::start(steps).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Some(result) = workflow.poll().unwrap() {
+                return (result.outcome, workflow.steps().iter().map(|s| s.state).collect());
+            }
+            assert!(Instant::now() < deadline, "workflow did not finish");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn failed_step_skips_the_rest() {
+        let (outcome, states) = run_workflow(vec![step("true"), step("false"), step("true")]);
+        assert_eq!(outcome, Outcome::Failed);
+        assert_eq!(states, [StepState::Succeeded, StepState::Failed, StepState::Skipped]);
+    }
+
+    #[test]
+    fn step_continuing_on_failure_lets_the_rest_run() {
+        let (outcome, states) = run_workflow(vec![step("false").continue_on_failure(), step("true")]);
+        assert_eq!(outcome, Outcome::Failed);
+        assert_eq!(states, [StepState::Failed, StepState::Succeeded]);
+    }
+
+    #[test]
+    fn passing_check_skips_the_steps_it_guards() {
+        let (outcome, states) = run_workflow(vec![check("true", 2), step("false"), step("false"), step("true")]);
+        assert_eq!(outcome, Outcome::Succeeded);
+        assert_eq!(states, [StepState::Succeeded, StepState::Satisfied, StepState::Satisfied, StepState::Succeeded]);
+    }
+
+    #[test]
+    fn failing_check_runs_the_steps_it_guards() {
+        let (outcome, states) = run_workflow(vec![check("false", 1), step("true"), step("true")]);
+        assert_eq!(outcome, Outcome::Succeeded);
+        assert_eq!(states, [StepState::NotReady, StepState::Succeeded, StepState::Succeeded]);
+
+        let (outcome, states) = run_workflow(vec![check("false", 1), step("false"), step("true")]);
+        assert_eq!(outcome, Outcome::Failed);
+        assert_eq!(states, [StepState::NotReady, StepState::Failed, StepState::Skipped]);
+    }
+
+    #[test]
+    fn batch_runs_at_most_limit_hosts_at_once() {
+        let stub = stub_ssh("batch-limit");
+        let items = ["a", "b", "c", "d", "e"].iter().map(|alias| host_item(&stub, alias)).collect();
+        let mut batch = Batch::start(items, 2);
+
+        assert_eq!(run(&mut batch), Outcome::Succeeded);
+        for item in batch.items() {
+            assert_eq!(item.state, StepState::Succeeded);
+            assert_eq!(item.output.trim(), format!("{}: uptime", item.name));
+        }
+        let counts = fs::read_to_string(stub.join("counts")).unwrap();
+        let counts: Vec<usize> = counts.lines().map(|line| line.trim().parse().unwrap()).collect();
+        assert_eq!(counts.len(), 5);
+        assert!(counts.iter().all(|&count| count <= 2), "{:?}", counts);
+        fs::remove_dir_all(stub).unwrap();
+    }
+
+    #[test]
+    fn batch_keeps_going_past_a_failed_host() {
+        let stub = stub_ssh("batch-failure");
+        let items = ["a", "broken", "c"].iter().map(|alias| host_item(&stub, alias)).collect();
+        let mut batch = Batch::start(items, 1);
+
+        assert_eq!(run(&mut batch), Outcome::Failed);
+        let states: Vec<_> = batch.items().iter().map(|item| item.state).collect();
+        assert_eq!(states, [StepState::Succeeded, StepState::Failed, StepState::Succeeded]);
+        fs::remove_dir_all(stub).unwrap();
+    }
+}
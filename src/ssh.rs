@@ -0,0 +1,208 @@
+use crate::privilege::Elevation;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A `Host` entry from `~/.ssh/config`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SshHost {
+    pub alias: String,
+    pub hostname: Option<String>,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+}
+
+impl SshHost {
+    /// `user@hostname:port` with whatever parts the config provides.
+    pub fn address(&self) -> String {
+        let mut address = self.hostname.clone().unwrap_or_else(|| self.alias.clone());
+        if let Some(user) = &self.user {
+            address = format!("{}@{}", user, address);
+        }
+        if let Some(port) = self.port {
+            address = format!("{}:{}", address, port);
+        }
+        address
+    }
+}
+
+fn config_file() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ssh").join("config"))
+}
+
+/// Reads the named hosts from `~/.ssh/config`. Wildcard patterns and
+/// `Match` blocks are skipped since they do not name a single host.
+pub fn load_hosts() -> Vec<SshHost> {
+    config_file()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|content| parse_hosts(&content))
+        .unwrap_or_default()
+}
+
+fn parse_hosts(content: &str) -> Vec<SshHost> {
+    let mut hosts: Vec<SshHost> = Vec::new();
+    // Index of the first host of the current `Host` block
+    let mut block: Option<usize> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let separator = |c: char| c.is_whitespace() || c == '=';
+        let Some((keyword, value)) = line.split_once(separator) else {
+            continue;
+        };
+        let keyword = keyword.to_lowercase();
+        let value = value.trim_start_matches(separator).trim();
+
+        match keyword.as_str() {
+            "host" => {
+                block = Some(hosts.len());
+                for alias in value.split_whitespace() {
+                    if !alias.contains(['*', '?', '!']) {
+                        hosts.push(SshHost {
+                            alias: alias.to_string(),
+                            hostname: None,
+                            user: None,
+                            port: None,
+                        });
+                    }
+                }
+            }
+            "match" => block = None,
+            _ => {
+                let Some(start) = block else { continue };
+                // ssh uses the first value it finds for each option
+                for host in &mut hosts[start..] {
+                    match keyword.as_str() {
+                        "hostname" => {
+                            host.hostname.get_or_insert_with(|| value.to_string());
+                        }
+                        "user" => {
+                            host.user.get_or_insert_with(|| value.to_string());
+                        }
+                        "port" if host.port.is_none() => host.port = value.parse().ok(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    hosts
+}
+
+/// Quotes an argument for the remote POSIX shell.
+pub fn quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:@,+%".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// The command line the remote shell runs for a program.
+pub fn remote_command(command: &str, args: &[String], elevation: Option<&Elevation>, interactive: bool) -> String {
+    let mut words = Vec::new();
+    if let Some(elevation) = elevation {
+        words.extend(elevation.prefix(interactive).iter().map(|word| quote(word)));
+    }
+    words.push(quote(command));
+    words.extend(args.iter().map(|arg| quote(arg)));
+    words.join(" ")
+}
+
+/// Wraps a remote command line in `ssh`. Interactive runs get a terminal;
+/// captured runs never prompt, so a missing key fails instead of hanging.
+pub fn command(host: &SshHost, remote: &str, interactive: bool) -> Command {
+    let mut cmd = Command::new("ssh");
+    if interactive {
+        cmd.arg("-t");
+    } else {
+        cmd.args(["-o", "BatchMode=yes"]);
+    }
+    if let Some(port) = host.port {
+        cmd.arg("-p").arg(port.to_string());
+    }
+    if let Some(user) = &host.user {
+        cmd.arg("-l").arg(user);
+    }
+    cmd.arg(host.hostname.as_deref().unwrap_or(&host.alias)).arg("--").arg(remote);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::privilege::Backend;
+
+    #[test]
+    fn parses_hosts_with_their_options() {
+        let hosts = parse_hosts(
+            "# comment\n\
+             Host web db\n\
+             \x20 HostName 10.0.0.1\n\
+             \x20 User=deploy\n\
+             \x20 Port 2222\n\
+             \x20 User ignored\n\
+             Host *.internal\n\
+             \x20 User nobody\n\
+             Host bastion\n",
+        );
+        let web = SshHost {
+            alias: "web".to_string(),
+            hostname: Some("10.0.0.1".to_string()),
+            user: Some("deploy".to_string()),
+            port: Some(2222),
+        };
+        let db = SshHost { alias: "db".to_string(), ..web.clone() };
+        let bastion = SshHost { alias: "bastion".to_string(), hostname: None, user: None, port: None };
+        assert_eq!(hosts, [web, db, bastion]);
+    }
+
+    #[test]
+    fn skips_options_of_match_blocks() {
+        let hosts = parse_hosts("Host a\nMatch user root\nPort 22\nhost b\nPORT=23\n");
+        assert_eq!(hosts[0].port, None);
+        assert_eq!(hosts[1].port, Some(23));
+        assert_eq!(hosts[1].address(), "b:23");
+    }
+
+    #[test]
+    fn quotes_only_what_the_shell_would_split() {
+        assert_eq!(quote("plain-word_1.2/x=y:z@h,a+b%"), "plain-word_1.2/x=y:z@h,a+b%");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("two words"), "'two words'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote("$HOME;rm"), "'$HOME;rm'");
+    }
+
+    #[test]
+    fn remote_command_quotes_every_word() {
+        let args = ["-n".to_string(), "a b".to_string()];
+        assert_eq!(remote_command("echo", &args, None, false), "echo -n 'a b'");
+
+        let elevation = Elevation { backend: Backend::Sudo, run_as: Some("www data".to_string()) };
+        assert_eq!(remote_command("ls", &[], Some(&elevation), false), "sudo -n -u 'www data' ls");
+    }
+
+    #[test]
+    fn command_passes_the_remote_line_as_one_argument() {
+        let host = SshHost {
+            alias: "web".to_string(),
+            hostname: Some("10.0.0.1".to_string()),
+            user: Some("deploy".to_string()),
+            port: Some(2222),
+        };
+        let cmd = command(&host, "echo 'a b'", false);
+        let args: Vec<_> = cmd.get_args().map(|arg| arg.to_str().unwrap()).collect();
+        assert_eq!(cmd.get_program(), "ssh");
+        assert_eq!(args, ["-o", "BatchMode=yes", "-p", "2222", "-l", "deploy", "10.0.0.1", "--", "echo 'a b'"]);
+
+        let bare = SshHost { alias: "web".to_string(), hostname: None, user: None, port: None };
+        let args: Vec<_> = command(&bare, "top", true).get_args().map(|arg| arg.to_str().unwrap().to_string()).collect();
+        assert_eq!(args, ["-t", "web", "--", "top"]);
+    }
+}
@@ -0,0 +1,38 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// How long sudo keeps credentials after they were last used, assuming the
+/// default `timestamp_timeout`. Only used for the indicator; launches still
+/// probe sudo itself.
+pub const CACHE_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// Whether sudo can run without asking for a password, because the
+/// credentials are cached or the user needs none.
+pub fn is_cached() -> bool {
+    Command::new("sudo")
+        .args(["-n", "true"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Caches sudo credentials with the given password. Later sudo runs of the
+/// dashboard's children then go through without a prompt. Returns false
+/// when the password is wrong.
+pub fn authenticate(password: &str) -> io::Result<bool> {
+    let mut child = Command::new("sudo")
+        .args(["-S", "-v", "-p", ""])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A wrong password makes sudo ask again; closing stdin ends that
+        let _ = writeln!(stdin, "{}", password);
+    }
+    Ok(child.wait()?.success())
+}
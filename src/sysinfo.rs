@@ -0,0 +1,51 @@
+use std::fs;
+use std::time::Duration;
+
+pub fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: gethostname writes at most `buf.len()` bytes into the buffer
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } != 0 {
+        return None;
+    }
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..end]).into_owned())
+}
+
+pub fn user() -> Option<String> {
+    std::env::var("USER").or_else(|_| std::env::var("LOGNAME")).ok()
+}
+
+/// The 1, 5 and 15 minute load averages.
+pub fn load_average() -> Option<(f64, f64, f64)> {
+    let content = fs::read_to_string("/proc/loadavg").ok()?;
+    let mut fields = content.split_whitespace().map(|field| field.parse().ok());
+    Some((fields.next()??, fields.next()??, fields.next()??))
+}
+
+pub fn uptime() -> Option<Duration> {
+    let content = fs::read_to_string("/proc/uptime").ok()?;
+    let seconds: f64 = content.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// Uptime the way `uptime` shows it, e.g. `3d 4h` or `12m`.
+pub fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+/// The current local time, broken down.
+pub fn local_time() -> Option<libc::tm> {
+    // SAFETY: time accepts a null pointer; localtime_r only writes to the
+    // tm struct it is given
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        (!libc::localtime_r(&now, &mut tm).is_null()).then_some(tm)
+    }
+}
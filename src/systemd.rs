@@ -0,0 +1,109 @@
+use crate::config::{FailurePolicy, LogSource, ProgramEntry};
+use serde::Deserialize;
+
+/// A service unit as listed by `systemctl list-units`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Unit {
+    #[serde(rename = "unit")]
+    pub name: String,
+    pub active: String,
+    pub sub: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl Unit {
+    pub fn is_failed(&self) -> bool {
+        self.active == "failed"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Start,
+    Stop,
+    Restart,
+    Status,
+}
+
+impl Action {
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Start => "start",
+            Action::Stop => "stop",
+            Action::Restart => "restart",
+            Action::Status => "status",
+        }
+    }
+
+    /// Actions that change the unit need root; they run in the terminal so
+    /// sudo can ask for a password. The others only read and are captured.
+    pub fn changes_state(self) -> bool {
+        matches!(self, Action::Start | Action::Stop | Action::Restart)
+    }
+
+    fn command(self, unit: &str) -> (&'static str, Vec<String>) {
+        let args = [self.label(), unit, "--no-pager"];
+        ("systemctl", args.into_iter().map(String::from).collect())
+    }
+}
+
+pub const LIST_COMMAND: &str = "systemctl";
+
+pub fn list_args() -> Vec<String> {
+    ["list-units", "--type=service", "--all", "--output=json", "--no-pager"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+pub fn parse_units(output: &str) -> serde_json::Result<Vec<Unit>> {
+    let mut units: Vec<Unit> = serde_json::from_str(output)?;
+    units.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(units)
+}
+
+/// An entry that runs an action on a unit, so it goes through the same
+/// launch path as configured entries.
+pub fn entry(unit: &Unit, action: Action) -> ProgramEntry {
+    let (command, args) = action.command(&unit.name);
+    ProgramEntry {
+        name: format!("{}:{}", unit.name, action.label()),
+        display_name: format!("{} {}", action.label(), unit.name),
+        command: command.to_string(),
+        args,
+        description: (!unit.description.is_empty()).then(|| unit.description.clone()),
+        run_with_sudo: action.changes_state(),
+        show_output: !action.changes_state(),
+        timeout: None,
+        embedded: false,
+        steps: Vec::new(),
+        on_failure: FailurePolicy::Stop,
+        depends_on: Vec::new(),
+        check: None,
+        host: None,
+        hosts: Vec::new(),
+        parallel: None,
+        container: None,
+        logs: None,
+        run_as: None,
+        privilege: None,
+        hotkey: None,
+        group: None,
+        color: false,
+    }
+}
+
+/// A dashboard entry showing the unit's status.
+pub fn pinned_entry(unit: &Unit, host: Option<String>) -> ProgramEntry {
+    ProgramEntry {
+        name: match &host {
+            Some(host) => format!("{}@{}", unit.name, host),
+            None => unit.name.clone(),
+        },
+        display_name: unit.name.clone(),
+        host,
+        logs: Some(LogSource::Unit(unit.name.clone())),
+        ..entry(unit, Action::Status)
+    }
+}
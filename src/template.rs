@@ -0,0 +1,85 @@
+/// Placeholders the header, footer and quote may use.
+pub const PLACEHOLDERS: &[&str] = &["hostname", "user", "time", "date", "load", "uptime", "host", "jobs", "keys"];
+
+/// Replaces each `{name}` with its value; `{{` and `}}` stand for braces.
+/// Placeholders without a value are left empty.
+pub fn render(template: &str, mut value: impl FnMut(&str) -> Option<String>) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        out.push_str(&rest[..start]);
+        let brace = &rest[start..start + 1];
+        rest = &rest[start + 1..];
+        if let Some(after) = rest.strip_prefix(brace) {
+            out.push_str(brace);
+            rest = after;
+        } else if brace == "{" {
+            match rest.split_once('}') {
+                Some((name, after)) => {
+                    out.push_str(&value(name).unwrap_or_default());
+                    rest = after;
+                }
+                None => out.push('{'),
+            }
+        } else {
+            out.push('}');
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Checks that a template only uses known placeholders.
+pub fn check(template: &str) -> Result<(), String> {
+    let mut unknown = None;
+    render(template, |name| {
+        if !PLACEHOLDERS.contains(&name) {
+            unknown.get_or_insert_with(|| name.to_string());
+        }
+        None
+    });
+    match unknown {
+        Some(name) => Err(format!("unknown placeholder {{{}}} (known: {})", name, PLACEHOLDERS.join(", "))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(name: &str) -> Option<String> {
+        match name {
+            "user" => Some("alice".to_string()),
+            "host" => Some("web".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn replaces_placeholders() {
+        assert_eq!(render("{user}@{host}", values), "alice@web");
+        assert_eq!(render("load: {load}.", values), "load: .");
+        assert_eq!(render("no placeholders", values), "no placeholders");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{user}} is {user}", values), "{user} is alice");
+        assert_eq!(render("a }} b", values), "a } b");
+    }
+
+    #[test]
+    fn unmatched_braces_are_kept() {
+        assert_eq!(render("{user", values), "{user");
+        assert_eq!(render("user}", values), "user}");
+        assert_eq!(render("{ä}é{host}", values), "éweb");
+    }
+
+    #[test]
+    fn check_rejects_unknown_placeholders() {
+        assert_eq!(check("{hostname} {{nope}} {keys}"), Ok(()));
+        let err = check("{load} {nope} {other}").unwrap_err();
+        assert!(err.starts_with("unknown placeholder {nope} (known: hostname, user,"), "{}", err);
+    }
+}
@@ -0,0 +1,141 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Columns between tab stops in captured output.
+const TAB_WIDTH: usize = 8;
+
+/// Terminal columns the text takes up: two for wide (CJK) characters,
+/// none for combining marks.
+pub fn width(text: &str) -> usize {
+    text.graphemes(true).map(UnicodeWidthStr::width).sum()
+}
+
+/// Cuts the text to at most `max` columns, ending it with `…` when anything
+/// was left out. Never splits a character from its combining marks.
+pub fn truncate(text: &str, max: usize) -> String {
+    if width(text) <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let (mut out, _) = take_width(text, max - 1);
+    out.push('…');
+    out
+}
+
+/// The longest start of the text that fits in `max` columns, and the
+/// columns it takes.
+pub fn take_width(text: &str, max: usize) -> (String, usize) {
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let grapheme_width = grapheme.width();
+        if used + grapheme_width > max {
+            break;
+        }
+        out.push_str(grapheme);
+        used += grapheme_width;
+    }
+    (out, used)
+}
+
+/// The text cut or padded with spaces to exactly `columns` columns, for
+/// bars that span the screen.
+pub fn fit(text: &str, columns: usize) -> String {
+    let mut out = truncate(text, columns);
+    let padding = columns.saturating_sub(width(&out));
+    out.extend(std::iter::repeat_n(' ', padding));
+    out
+}
+
+/// First column that centres text of the given width in `columns`, or
+/// `margin` when it does not fit.
+pub fn centered(text_width: usize, columns: u16, margin: u16) -> u16 {
+    match (columns as usize).checked_sub(text_width) {
+        Some(space) if space > 0 => (space / 2) as u16,
+        _ => margin,
+    }
+}
+
+/// Captured output made safe to print: tabs expanded to the next stop and
+/// other control characters shown in caret notation (`^[`). `column` is
+/// where the text starts on its line and is moved past it.
+pub fn escape_controls(text: &str, column: &mut usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    for grapheme in text.graphemes(true) {
+        let mut chars = grapheme.chars();
+        match (chars.next(), chars.next()) {
+            (Some('\t'), None) => {
+                let spaces = TAB_WIDTH - *column % TAB_WIDTH;
+                out.extend(std::iter::repeat_n(' ', spaces));
+                *column += spaces;
+            }
+            (Some(c @ ('\0'..='\x1f' | '\x7f')), None) => {
+                out.push('^');
+                out.push(((c as u8) ^ 0x40) as char);
+                *column += 2;
+            }
+            (Some('\u{80}'..='\u{9f}'), None) => {
+                out.push(char::REPLACEMENT_CHARACTER);
+                *column += 1;
+            }
+            _ => {
+                out.push_str(grapheme);
+                *column += grapheme.width();
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_marks_cut_text() {
+        assert_eq!(truncate("hello", 5), "hello");
+        assert_eq!(truncate("hello", 4), "hel…");
+        assert_eq!(truncate("hello", 1), "…");
+        assert_eq!(truncate("hello", 0), "");
+        assert_eq!(truncate("", 0), "");
+    }
+
+    #[test]
+    fn truncate_counts_columns_not_chars() {
+        assert_eq!(truncate("日本語", 6), "日本語");
+        assert_eq!(truncate("日本語", 4), "日…");
+        // A combining accent stays with its letter
+        assert_eq!(truncate("e\u{301}e\u{301}e\u{301}", 2), "e\u{301}…");
+    }
+
+    #[test]
+    fn fit_pads_or_cuts_to_the_width() {
+        assert_eq!(fit("ab", 4), "ab  ");
+        assert_eq!(fit("abcdef", 4), "abc…");
+        assert_eq!(fit("日本", 3), "日…");
+        assert_eq!(fit("日本語", 5), "日本…");
+        assert_eq!(width(&fit("日本語", 4)), 4);
+    }
+
+    #[test]
+    fn escape_controls_expands_tabs_to_stops() {
+        let mut column = 0;
+        assert_eq!(escape_controls("a\tb", &mut column), "a       b");
+        assert_eq!(column, 9);
+        let mut column = 6;
+        assert_eq!(escape_controls("\tx", &mut column), "  x");
+        assert_eq!(column, 9);
+    }
+
+    #[test]
+    fn escape_controls_shows_control_characters() {
+        let mut column = 0;
+        assert_eq!(escape_controls("\x1b[31mred\x7f\0", &mut column), "^[[31mred^?^@");
+        assert_eq!(column, 13);
+        let mut column = 0;
+        assert_eq!(escape_controls("a\u{9b}日", &mut column), "a\u{fffd}日");
+        assert_eq!(column, 4);
+    }
+}
@@ -0,0 +1,316 @@
+use crossterm::style::{Attribute, Color, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use crate::vt;
+use crossterm::Command;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Built-in theme names, for errors and the README.
+pub const BUILT_IN: &[&str] = &["dark", "light", "solarized", "high-contrast", "monochrome"];
+
+/// Colours and attributes for one kind of text. Used as a crossterm command
+/// before printing; `ResetColor` afterwards clears all of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub reverse: bool,
+}
+
+impl Style {
+    const PLAIN: Style = Style { fg: None, bg: None, bold: false, reverse: false };
+
+    const fn fg(fg: Color) -> Self {
+        Style { fg: Some(fg), ..Self::PLAIN }
+    }
+
+    const fn on(fg: Color, bg: Color) -> Self {
+        Style { fg: Some(fg), bg: Some(bg), ..Self::PLAIN }
+    }
+
+    const fn bold(self) -> Self {
+        Style { bold: true, ..self }
+    }
+
+    const fn reverse(self) -> Self {
+        Style { reverse: true, ..self }
+    }
+}
+
+impl Command for Style {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if let Some(fg) = self.fg {
+            SetForegroundColor(fg).write_ansi(f)?;
+        }
+        if let Some(bg) = self.bg {
+            SetBackgroundColor(bg).write_ansi(f)?;
+        }
+        if self.bold {
+            SetAttribute(Attribute::Bold).write_ansi(f)?;
+        }
+        if self.reverse {
+            SetAttribute(Attribute::Reverse).write_ansi(f)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The same look as a cell style, for text that mixes theme styles with
+/// the colours of captured output.
+impl From<Style> for vt::Style {
+    fn from(style: Style) -> Self {
+        vt::Style {
+            fg: style.fg.unwrap_or(Color::Reset),
+            bg: style.bg.unwrap_or(Color::Reset),
+            bold: style.bold,
+            underline: false,
+            reverse: style.reverse,
+        }
+    }
+}
+
+/// The styles of every part of the screen.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Top bar of the main screen.
+    pub header: Style,
+    /// Top bar of the list and output screens.
+    pub panel_header: Style,
+    /// Top bar of the help and password screens.
+    pub help_header: Style,
+    /// Top bar of the add form.
+    pub form_header: Style,
+    pub footer: Style,
+    /// Keys typed so far, at the right of the footer.
+    pub pending: Style,
+    pub selected: Style,
+    /// Entries picked for batch actions.
+    pub marked: Style,
+    pub dim: Style,
+    pub badge: Style,
+    pub success: Style,
+    pub warning: Style,
+    pub error: Style,
+}
+
+/// A theme in the config: a built-in one to start from and the styles that
+/// differ from it, by name (`header`, `panel-header`, ...).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThemeSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(flatten)]
+    pub styles: HashMap<String, StyleSpec>,
+}
+
+/// Colours are names (`blue`, `dark-grey`), 256-colour numbers (`208`) or
+/// `#rrggbb`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StyleSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bg: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reverse: bool,
+}
+
+impl Theme {
+    /// The theme to draw with: `monochrome` when `NO_COLOR` is set,
+    /// otherwise the named theme, `dark` by default.
+    pub fn load(name: Option<&str>, themes: &HashMap<String, ThemeSpec>) -> Result<Self, String> {
+        let theme = Self::named(name.unwrap_or("dark"), themes, 0)?;
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Ok(if no_color { Self::monochrome() } else { theme })
+    }
+
+    fn named(name: &str, themes: &HashMap<String, ThemeSpec>, depth: usize) -> Result<Self, String> {
+        let Some(spec) = themes.get(name) else {
+            return Self::built_in(name).ok_or_else(|| {
+                format!("Unknown theme '{}' (built in: {})", name, BUILT_IN.join(", "))
+            });
+        };
+        // A theme may be based on another user theme, but not in a circle
+        if depth > themes.len() {
+            return Err(format!("Theme '{}' is based on itself through other themes", name));
+        }
+        let base = match spec.base.as_deref() {
+            Some(base) if base != name => Self::named(base, themes, depth + 1)?,
+            _ => Self::built_in(spec.base.as_deref().unwrap_or("dark"))
+                .ok_or_else(|| format!("Theme '{}' has an unknown base", name))?,
+        };
+        base.with(spec).map_err(|e| format!("Theme '{}': {}", name, e))
+    }
+
+    fn with(mut self, spec: &ThemeSpec) -> Result<Self, String> {
+        for (role, style) in &spec.styles {
+            let target = match role.as_str() {
+                "header" => &mut self.header,
+                "panel-header" => &mut self.panel_header,
+                "help-header" => &mut self.help_header,
+                "form-header" => &mut self.form_header,
+                "footer" => &mut self.footer,
+                "pending" => &mut self.pending,
+                "selected" => &mut self.selected,
+                "marked" => &mut self.marked,
+                "dim" => &mut self.dim,
+                "badge" => &mut self.badge,
+                "success" => &mut self.success,
+                "warning" => &mut self.warning,
+                "error" => &mut self.error,
+                _ => return Err(format!("unknown style '{}'", role)),
+            };
+            *target = Style {
+                fg: style.fg.as_deref().map(parse_color).transpose()?,
+                bg: style.bg.as_deref().map(parse_color).transpose()?,
+                bold: style.bold,
+                reverse: style.reverse,
+            };
+        }
+        Ok(self)
+    }
+
+    fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "solarized" => Some(Self::solarized()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    fn dark() -> Self {
+        Self {
+            header: Style::on(Color::White, Color::Blue),
+            panel_header: Style::on(Color::Black, Color::Cyan),
+            help_header: Style::on(Color::White, Color::Magenta),
+            form_header: Style::on(Color::White, Color::Green),
+            footer: Style::on(Color::White, Color::DarkGrey),
+            pending: Style::on(Color::Yellow, Color::DarkGrey),
+            selected: Style::on(Color::Black, Color::Yellow),
+            marked: Style::on(Color::White, Color::Blue),
+            dim: Style::fg(Color::DarkGrey),
+            badge: Style::fg(Color::Cyan),
+            success: Style::fg(Color::Green),
+            warning: Style::fg(Color::Yellow),
+            error: Style::fg(Color::Red),
+        }
+    }
+
+    /// For dark text on a light background: the darker variants of each
+    /// colour, which stay readable on white.
+    fn light() -> Self {
+        Self {
+            header: Style::on(Color::White, Color::DarkBlue),
+            panel_header: Style::on(Color::White, Color::DarkCyan),
+            help_header: Style::on(Color::White, Color::DarkMagenta),
+            form_header: Style::on(Color::White, Color::DarkGreen),
+            footer: Style::on(Color::Black, Color::Grey),
+            pending: Style::on(Color::DarkRed, Color::Grey),
+            selected: Style::on(Color::Black, Color::Yellow),
+            marked: Style::on(Color::White, Color::DarkBlue),
+            dim: Style::fg(Color::DarkGrey),
+            badge: Style::fg(Color::DarkCyan),
+            success: Style::fg(Color::DarkGreen),
+            warning: Style::fg(Color::DarkYellow),
+            error: Style::fg(Color::DarkRed),
+        }
+    }
+
+    fn solarized() -> Self {
+        let rgb = |r, g, b| Color::Rgb { r, g, b };
+        let base03 = rgb(0x00, 0x2b, 0x36);
+        let base02 = rgb(0x07, 0x36, 0x42);
+        let base01 = rgb(0x58, 0x6e, 0x75);
+        let base1 = rgb(0x93, 0xa1, 0xa1);
+        let base3 = rgb(0xfd, 0xf6, 0xe3);
+        let yellow = rgb(0xb5, 0x89, 0x00);
+        let red = rgb(0xdc, 0x32, 0x2f);
+        let magenta = rgb(0xd3, 0x36, 0x82);
+        let violet = rgb(0x6c, 0x71, 0xc4);
+        let blue = rgb(0x26, 0x8b, 0xd2);
+        let cyan = rgb(0x2a, 0xa1, 0x98);
+        let green = rgb(0x85, 0x99, 0x00);
+        Self {
+            header: Style::on(base3, blue),
+            panel_header: Style::on(base03, cyan),
+            help_header: Style::on(base3, magenta),
+            form_header: Style::on(base03, green),
+            footer: Style::on(base1, base02),
+            pending: Style::on(yellow, base02),
+            selected: Style::on(base03, yellow),
+            marked: Style::on(base3, violet),
+            dim: Style::fg(base01),
+            badge: Style::fg(cyan),
+            success: Style::fg(green),
+            warning: Style::fg(yellow),
+            error: Style::fg(red),
+        }
+    }
+
+    fn high_contrast() -> Self {
+        Self {
+            header: Style::on(Color::Black, Color::White).bold(),
+            panel_header: Style::on(Color::Black, Color::White).bold(),
+            help_header: Style::on(Color::Black, Color::White).bold(),
+            form_header: Style::on(Color::Black, Color::White).bold(),
+            footer: Style::on(Color::Black, Color::White),
+            pending: Style::on(Color::Black, Color::Yellow).bold(),
+            selected: Style::on(Color::Black, Color::Yellow).bold(),
+            marked: Style::on(Color::Black, Color::Cyan),
+            dim: Style::fg(Color::White),
+            badge: Style::fg(Color::Cyan).bold(),
+            success: Style::fg(Color::Green).bold(),
+            warning: Style::fg(Color::Yellow).bold(),
+            error: Style::fg(Color::Red).bold(),
+        }
+    }
+
+    /// No colours at all; bars and the selection are set apart by reverse
+    /// video and bold text instead.
+    fn monochrome() -> Self {
+        let bar = Style::PLAIN.reverse();
+        Self {
+            header: bar,
+            panel_header: bar,
+            help_header: bar,
+            form_header: bar,
+            footer: bar,
+            pending: bar.bold(),
+            selected: bar,
+            marked: Style::PLAIN.bold(),
+            dim: Style::PLAIN,
+            badge: Style::PLAIN.bold(),
+            success: Style::PLAIN,
+            warning: Style::PLAIN.bold(),
+            error: Style::PLAIN.bold(),
+        }
+    }
+}
+
+fn parse_color(text: &str) -> Result<Color, String> {
+    if let Some(hex) = text.strip_prefix('#') {
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb { r, g, b }),
+            _ => Err(format!("invalid colour '{}'", text)),
+        };
+    }
+    if let Ok(value) = text.parse::<u8>() {
+        return Ok(Color::AnsiValue(value));
+    }
+    match text.to_lowercase().replace('-', "_").as_str() {
+        "default" | "reset" => Ok(Color::Reset),
+        name => Color::try_from(name).map_err(|_| format!("invalid colour '{}'", text)),
+    }
+}
@@ -1,13 +1,86 @@
-use crate::config::{Config, ProgramEntry};
+use crate::ansi::{self, Span};
+use crate::clipboard;
+use crate::command::{self, ExCommand};
+use crate::container::{self, Container};
+use crate::config::{Config, FailurePolicy, LogSource, MetricsLayout, ProgramEntry, Quote, SortOrder};
+use crate::keymap::{Action, Hotkey, KeyChord, KeyMode, Keymap};
+use crate::logs::{self, LogTail};
+use crate::metrics::{self, Metrics, Monitor};
+use crate::motion::{KeySequence, Motion, Parsed};
+use crate::pty::PtySession;
+use crate::render::Renderer;
+use crate::runner::{Batch, BatchItem, Job, JobEnd, Outcome, StepState, Workflow, WorkflowStep};
+use crate::privilege::{Backend, Elevation};
+use crate::ssh::{self, SshHost};
+use crate::sudo;
+use crate::sysinfo;
+use crate::template;
+use crate::text;
+use crate::theme::{Style, Theme};
+use crate::systemd::{self, Unit};
+use crate::vt::{self, Screen};
+use regex::Regex;
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
-    event::{self, Event, KeyCode, KeyEvent},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     execute,
-    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    queue,
+    style::{Attribute, Print, ResetColor, SetAttribute},
     terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::io::{self, Write};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::PathBuf;
 use std::process::Command;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How often running jobs and the embedded pane are checked while waiting
+/// for input.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the screen is redrawn with nothing else happening, for
+/// elapsed times, the clock and the metrics.
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long listing containers or units, possibly over ssh, may take
+/// before it is given up.
+const LIST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Longest time between two clicks on an entry that launches it.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Lines of output one step of the scroll wheel moves.
+const SCROLL_LINES: usize = 3;
+
+/// How many hosts a multi-host entry runs on at once unless it sets
+/// `parallel`.
+const DEFAULT_PARALLEL_HOSTS: usize = 4;
+
+/// Variables that make most programs colour their output when it goes to
+/// a pipe, set for entries with `color`.
+const COLOR_ENV: &[&str] = &["CLICOLOR_FORCE=1", "FORCE_COLOR=1"];
+
+/// Screen row of the first entry in the list.
+const LIST_TOP: u16 = 4;
+
+/// Where the batch actions export the selection unless told otherwise.
+const DEFAULT_EXPORT_PATH: &str = "~/rdash-selection.json";
+
+/// Columns of the metrics sidebar, its separator included.
+const SIDEBAR_WIDTH: u16 = 30;
+
+/// Narrowest terminal the metrics sidebar is shown in; narrower ones get
+/// the header line instead.
+const SIDEBAR_MIN_WIDTH: u16 = 100;
+
+/// How long the quote command may take before it is stopped.
+const QUOTE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long each line of a quote list is shown.
+const QUOTE_ROTATION: Duration = Duration::from_secs(60);
 
 pub struct Dashboard {
     config: Config,
@@ -15,7 +88,69 @@ pub struct Dashboard {
     mode: Mode,
     add_form: AddProgramForm,
     status_message: Option<String>,
-    output_data: Option<(String, String)>, // (program_name, output)
+    output_view: Option<OutputView>,
+    job: Option<(String, Vec<String>, Workflow)>, // (display_name, launched program names, workflow)
+    pane: Option<(String, PtySession)>,    // (program_name, session)
+    focus: Focus,
+    pane_hidden: bool,
+    pending_launch: Option<String>, // entry to launch once its prerequisites succeed
+    batch: Option<(String, Batch)>,  // (program_name, batch)
+    batch_index: usize,
+    output_return: Mode,
+    active_host: Option<String>, // host for entries without their own; None runs locally
+    host_index: usize,
+    container_pick: Option<(ProgramEntry, Vec<Container>)>, // entry waiting for a container
+    container_index: usize,
+    units: Vec<Unit>,
+    unit_index: usize,
+    failed_units_only: bool,
+    log_view: Option<LogView>,
+    sudo_prompt: Option<SudoPrompt>,
+    sudo_cached_at: Option<Instant>,
+    command_line: CommandLine,
+    keys: KeySequence,
+    keymap: Keymap,
+    theme: Theme,
+    leader_pending: bool,
+    marks: HashMap<char, String>, // mark -> program name
+    jumps: Vec<String>,           // program names, oldest first
+    jump_index: usize,            // position in `jumps`; its length when not jumping back
+    list_offset: usize,           // first entry shown in the list
+    selection: HashSet<String>,   // program names picked for batch actions
+    visual_anchor: Option<String>, // program where visual mode started
+    selection_menu: SelectionMenu,
+    batch_kind: BatchKind,
+    command_quote: Option<String>,          // output of the quote command once it finished
+    quote_command: Option<Job>,             // quote command still running
+    monitor: Option<Monitor>,               // system metrics, when shown
+    renderer: Renderer,
+    last_click: Option<(Instant, usize)>, // when and on which entry, for double clicks
+    listing: Option<(Listing, Job)>,      // list being fetched for a view
+}
+
+/// What a list fetched in the background is for.
+enum Listing {
+    /// The running containers, to pick one to launch the entry in.
+    Containers(Box<ProgramEntry>),
+    /// The systemd units of the active host, for the unit view.
+    Units,
+}
+
+impl Listing {
+    fn noun(&self) -> &'static str {
+        match self {
+            Listing::Containers(_) => "containers",
+            Listing::Units => "units",
+        }
+    }
+}
+
+/// Which side of the main screen receives key presses while an embedded
+/// program is running.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Focus {
+    List,
+    Pane,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -24,6 +159,283 @@ enum Mode {
     AddProgram,
     Help,
     ShowOutput,
+    Progress,
+    Batch,
+    HostSwitcher,
+    ContainerPicker,
+    Systemd,
+    Logs,
+    SudoPrompt,
+    Command,
+    Selection,
+}
+
+/// What the items of the running batch are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BatchKind {
+    Hosts,
+    Programs,
+}
+
+impl BatchKind {
+    fn noun(self) -> &'static str {
+        match self {
+            BatchKind::Hosts => "hosts",
+            BatchKind::Programs => "programs",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            BatchKind::Hosts => "Hosts",
+            BatchKind::Programs => "Batch",
+        }
+    }
+
+    fn headings(self) -> (&'static str, &'static str) {
+        match self {
+            BatchKind::Hosts => ("HOST", "ADDRESS"),
+            BatchKind::Programs => ("PROGRAM", "WHERE"),
+        }
+    }
+}
+
+/// Something done to all selected entries at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SelectionAction {
+    LaunchInOrder,
+    LaunchTogether,
+    Delete,
+    Group,
+    Export,
+}
+
+impl SelectionAction {
+    const ALL: [SelectionAction; 5] = [
+        SelectionAction::LaunchInOrder,
+        SelectionAction::LaunchTogether,
+        SelectionAction::Delete,
+        SelectionAction::Group,
+        SelectionAction::Export,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            SelectionAction::LaunchInOrder => "Launch one after another",
+            SelectionAction::LaunchTogether => "Launch all at once",
+            SelectionAction::Delete => "Delete",
+            SelectionAction::Group => "Move to group",
+            SelectionAction::Export => "Export to a file",
+        }
+    }
+}
+
+/// The batch actions menu and the entries it acts on.
+#[derive(Debug, Default)]
+struct SelectionMenu {
+    names: Vec<String>, // in list order
+    index: usize,
+    prompt: Option<(SelectionAction, String)>, // action waiting for a confirmation, group or path
+}
+
+/// A followed log and how it is being viewed.
+struct LogView {
+    title: String,
+    tail: LogTail,
+    filter: Option<Regex>,
+    filter_input: Option<String>, // regex being typed after '/'
+    filter_error: Option<String>,
+    paused_at: Option<usize>, // line total when paused; None while following
+    scroll: usize,            // matching lines hidden below the view
+    ended: bool,
+    return_to: Mode,
+    drawn_total: usize, // line total when last drawn
+}
+
+/// Captured output being read: where it is scrolled to and what is being
+/// searched for.
+struct OutputView {
+    title: String,
+    lines: Vec<Vec<Span>>,
+    texts: Vec<String>, // plain text of each line, searched and copied
+    top: usize,         // first line shown
+    search: Option<Regex>,
+    current: Option<usize>,         // line of the match moved to last
+    prompt: Option<(char, String)>, // search ('/') or command (':') being typed
+    message: Option<String>,        // outcome of the last search, copy or save
+}
+
+impl OutputView {
+    fn new(title: String, output: &str) -> Self {
+        let lines = ansi::parse(output);
+        let texts = lines.iter().map(|line| ansi::plain_text(line)).collect();
+        OutputView { title, lines, texts, top: 0, search: None, current: None, prompt: None, message: None }
+    }
+
+    fn max_top(&self, rows: usize) -> usize {
+        self.lines.len().saturating_sub(rows)
+    }
+
+    fn scroll_to(&mut self, top: usize, rows: usize) {
+        self.top = top.min(self.max_top(rows));
+    }
+
+    /// Searches from the top of the view; an empty pattern ends the search.
+    fn search(&mut self, pattern: &str, rows: usize) {
+        self.current = None;
+        if pattern.is_empty() {
+            self.search = None;
+            return;
+        }
+        match Regex::new(pattern) {
+            Ok(search) => {
+                self.search = Some(search);
+                self.find(true, rows);
+            }
+            Err(e) => self.message = Some(e.to_string().lines().last().unwrap_or_default().to_string()),
+        }
+    }
+
+    /// Moves to the next or previous line with a match, wrapping around at
+    /// the ends, and scrolls it into view.
+    fn find(&mut self, forward: bool, rows: usize) {
+        let Some(search) = &self.search else {
+            self.message = Some("No search".to_string());
+            return;
+        };
+        let count = self.lines.len();
+        if count == 0 {
+            return;
+        }
+        // Without a match yet, the search starts at the top of the view
+        let origin = self.current.unwrap_or(if forward { self.top + count - 1 } else { self.top }) % count;
+        let found = (1..=count)
+            .map(|step| if forward { (origin + step) % count } else { (origin + count - step) % count })
+            .find(|&line| search.is_match(&self.texts[line]));
+
+        let Some(line) = found else {
+            self.message = Some(format!("Pattern not found: {}", search.as_str()));
+            return;
+        };
+        if self.current.is_some() && (forward && line <= origin || !forward && line >= origin) {
+            self.message = Some(format!("Search wrapped to the {}", if forward { "top" } else { "bottom" }));
+        }
+        self.current = Some(line);
+        if line < self.top || line >= self.top + rows {
+            self.scroll_to(line.saturating_sub(rows / 2), rows);
+        }
+    }
+
+    /// The lines a search matches, or the whole output without one, with
+    /// what they are.
+    fn selection(&self) -> (String, String) {
+        let lines: Vec<&str> = self
+            .texts
+            .iter()
+            .map(String::as_str)
+            .filter(|text| self.search.as_ref().is_none_or(|search| search.is_match(text)))
+            .collect();
+        let what = match &self.search {
+            Some(_) => format!("{} matching lines", lines.len()),
+            None => "the output".to_string(),
+        };
+        (what, lines.join("\n"))
+    }
+
+    /// Saves the output as shown, without colours.
+    /// Writes the lines to a file; one that exists is only replaced when
+    /// `overwrite` is set, as with Vim's `:w!`.
+    fn save(&self, path: &str, overwrite: bool) -> String {
+        let path = expand_home(path);
+        if !overwrite && path.exists() {
+            return format!("{} exists; use :w! to overwrite it", path.display());
+        }
+        let mut content = self.texts.join("\n");
+        content.push('\n');
+        match std::fs::write(&path, content) {
+            Ok(()) => format!("Saved {} lines to {}", self.texts.len(), path.display()),
+            Err(e) => format!("Error saving to {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// A path typed in, with `~/` standing for the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// The `:` command line and the commands entered this session.
+#[derive(Debug, Default)]
+struct CommandLine {
+    input: String,
+    history: Vec<String>,
+    history_index: Option<usize>,
+    completions: Vec<String>,
+    completion_index: Option<usize>,
+}
+
+impl CommandLine {
+    fn open(&mut self) {
+        self.input.clear();
+        self.history_index = None;
+        self.clear_completions();
+    }
+
+    fn clear_completions(&mut self) {
+        self.completions.clear();
+        self.completion_index = None;
+    }
+
+    /// Moves through the history, newest first; past the newest entry the
+    /// line is empty again.
+    fn recall(&mut self, older: bool) {
+        let index = match (self.history_index, older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => None,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) => Some(i + 1).filter(|&i| i < self.history.len()),
+        };
+        self.history_index = index;
+        self.input = index.map_or_else(String::new, |i| self.history[i].clone());
+    }
+
+    /// Replaces the line with the next (or previous) completion, working
+    /// out the candidates on the first press.
+    fn complete(&mut self, names: &[&str], forward: bool) {
+        if self.completion_index.is_none() {
+            self.completions = command::complete(&self.input, names);
+        }
+        if self.completions.is_empty() {
+            return;
+        }
+        let count = self.completions.len();
+        let index = match self.completion_index {
+            None if forward => 0,
+            None => count - 1,
+            Some(i) if forward => (i + 1) % count,
+            Some(i) => (i + count - 1) % count,
+        };
+        self.completion_index = Some(index);
+        self.input = self.completions[index].clone();
+    }
+
+    fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.input);
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        line
+    }
+}
+
+/// The password dialog shown before a captured run that needs sudo.
+struct SudoPrompt {
+    password: String,
+    error: Option<String>,
+    then: Option<ProgramEntry>, // entry to launch once authenticated
 }
 
 #[derive(Debug, Clone)]
@@ -103,8 +515,15 @@ impl AddProgramForm {
 impl Dashboard {
     pub fn new() -> io::Result<Self> {
         let config = Config::load().map_err(|e| {
-            io::Error::new(io::ErrorKind::Other, format!("Failed to load config: {}", e))
+            io::Error::other(format!("Failed to load config: {}", e))
         })?;
+        Self::with_config(config)
+    }
+
+    fn with_config(config: Config) -> io::Result<Self> {
+        let keymap = config.keymap().map_err(io::Error::other)?;
+        let theme = config.theme().map_err(io::Error::other)?;
+        let monitor = config.metrics.map(|_| Monitor::new());
 
         Ok(Self {
             config,
@@ -112,7 +531,44 @@ impl Dashboard {
             mode: Mode::Normal,
             add_form: AddProgramForm::new(),
             status_message: None,
-            output_data: None,
+            output_view: None,
+            job: None,
+            pane: None,
+            focus: Focus::List,
+            pane_hidden: false,
+            pending_launch: None,
+            batch: None,
+            batch_index: 0,
+            output_return: Mode::Normal,
+            active_host: None,
+            host_index: 0,
+            container_pick: None,
+            container_index: 0,
+            units: Vec::new(),
+            unit_index: 0,
+            failed_units_only: false,
+            log_view: None,
+            sudo_prompt: None,
+            sudo_cached_at: None,
+            command_line: CommandLine::default(),
+            keys: KeySequence::default(),
+            keymap,
+            theme,
+            leader_pending: false,
+            marks: HashMap::new(),
+            jumps: Vec::new(),
+            jump_index: 0,
+            list_offset: 0,
+            selection: HashSet::new(),
+            visual_anchor: None,
+            selection_menu: SelectionMenu::default(),
+            batch_kind: BatchKind::Hosts,
+            command_quote: None,
+            quote_command: None,
+            monitor,
+            renderer: Renderer::default(),
+            last_click: None,
+            listing: None,
         })
     }
 
@@ -120,84 +576,545 @@ impl Dashboard {
         // Enable raw mode and alternate screen
         terminal::enable_raw_mode()?;
         execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        self.capture_mouse()?;
 
+        self.start_quote_command();
         let result = self.main_loop();
 
         // Cleanup
-        execute!(io::stdout(), LeaveAlternateScreen, Show)?;
+        execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen, Show)?;
         terminal::disable_raw_mode()?;
 
         result
     }
 
+    /// Waits for whatever comes first of a terminal event, a tick or news
+    /// from the background work, and redraws when something changed.
     fn main_loop(&mut self) -> io::Result<()> {
+        let mut redraw = true;
+        let mut next_tick = Instant::now() + TICK_INTERVAL;
         loop {
-            self.draw()?;
+            if redraw {
+                self.draw()?;
+                redraw = false;
+            }
 
-            if let Event::Key(key) = event::read()? {
-                match self.mode {
-                    Mode::Normal => {
-                        if self.handle_normal_mode(key)? {
+            // Running work is checked every poll interval; otherwise only
+            // input or the tick wakes the loop
+            let mut timeout = next_tick.saturating_duration_since(Instant::now());
+            if self.has_background_work() {
+                timeout = timeout.min(POLL_INTERVAL);
+            }
+            if event::poll(timeout)? {
+                match event::read()? {
+                    Event::Key(key) => {
+                        if self.handle_key(key)? {
                             break;
                         }
+                        redraw = true;
                     }
-                    Mode::AddProgram => {
-                        self.handle_add_program_mode(key)?;
+                    Event::Mouse(mouse) => {
+                        if self.handle_mouse(mouse)? {
+                            break;
+                        }
+                        redraw |= mouse.kind != MouseEventKind::Moved;
                     }
-                    Mode::Help => {
-                        self.handle_help_mode(key);
+                    Event::Resize(..) => redraw = true,
+                    _ => {}
+                }
+            }
+
+            redraw |= self.poll_job()?;
+            redraw |= self.poll_batch()?;
+            redraw |= self.poll_pane()?;
+            redraw |= self.poll_logs()?;
+            redraw |= self.poll_quote()?;
+            redraw |= self.poll_listing()?;
+
+            // Elapsed times, the clock and the metrics move on every tick
+            if Instant::now() >= next_tick {
+                next_tick = Instant::now() + TICK_INTERVAL;
+                self.poll_metrics();
+                redraw = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Passes a key to the current mode; returns true to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> io::Result<bool> {
+        match self.mode {
+            Mode::Normal if self.pane_focused() => {
+                self.handle_pane_mode(key)?;
+            }
+            Mode::Normal => {
+                return self.handle_normal_mode(key);
+            }
+            Mode::AddProgram => {
+                self.handle_add_program_mode(key)?;
+            }
+            Mode::Help => {
+                self.handle_help_mode(key);
+            }
+            Mode::ShowOutput => {
+                self.handle_show_output_mode(key)?;
+            }
+            Mode::Progress => {
+                self.handle_progress_mode(key);
+            }
+            Mode::Batch => {
+                self.handle_batch_mode(key);
+            }
+            Mode::HostSwitcher => {
+                self.handle_host_switcher_mode(key);
+            }
+            Mode::ContainerPicker => {
+                self.handle_container_picker_mode(key)?;
+            }
+            Mode::Systemd => {
+                self.handle_systemd_mode(key)?;
+            }
+            Mode::Logs => {
+                self.handle_logs_mode(key);
+            }
+            Mode::SudoPrompt => {
+                self.handle_sudo_prompt_mode(key)?;
+            }
+            Mode::Command => {
+                return self.handle_command_mode(key);
+            }
+            Mode::Selection => {
+                self.handle_selection_mode(key)?;
+            }
+        }
+        Ok(false)
+    }
+
+    /// Turns the terminal's mouse reporting on or off, as the config says.
+    fn capture_mouse(&self) -> io::Result<()> {
+        if self.config.mouse {
+            execute!(io::stdout(), EnableMouseCapture)
+        } else {
+            execute!(io::stdout(), DisableMouseCapture)
+        }
+    }
+
+    /// Acts on a click or a turn of the scroll wheel; returns true to quit.
+    /// A click on a hint of the bottom bar presses its key.
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> io::Result<bool> {
+        let size = terminal::size()?;
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) if mouse.row + 1 == size.1 && self.footer_has_keys() => {
+                if let Some(chord) = self.footer_hint(mouse.row, mouse.column) {
+                    return self.handle_key(chord.to_event());
+                }
+            }
+            MouseEventKind::Down(MouseButton::Left) if self.mode == Mode::Normal => {
+                self.click_entry(mouse.column, mouse.row, size)?;
+            }
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+                let down = mouse.kind == MouseEventKind::ScrollDown;
+                match self.mode {
+                    Mode::Normal if !self.over_pane(mouse.row, size) => {
+                        let last = self.config.get_programs().len().saturating_sub(1);
+                        self.selected_index =
+                            if down { (self.selected_index + 1).min(last) } else { self.selected_index.saturating_sub(1) };
                     }
                     Mode::ShowOutput => {
-                        self.handle_show_output_mode(key);
+                        let rows = Self::output_rows(size);
+                        if let Some(view) = self.output_view.as_mut() {
+                            if down {
+                                view.scroll_to(view.top + SCROLL_LINES, rows);
+                            } else {
+                                view.top = view.top.saturating_sub(SCROLL_LINES);
+                            }
+                        }
                     }
+                    _ => {}
                 }
             }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Whether the bottom row shows the key bar; command lines, prompts and
+    /// messages there take typed text, not clicks.
+    fn footer_has_keys(&self) -> bool {
+        match self.mode {
+            Mode::Command | Mode::AddProgram | Mode::SudoPrompt => false,
+            Mode::Selection => self.selection_menu.prompt.is_none(),
+            Mode::ShowOutput => self.output_view.as_ref().is_some_and(|view| view.prompt.is_none() && view.message.is_none()),
+            Mode::Logs => self.log_view.as_ref().is_some_and(|view| view.filter_input.is_none()),
+            _ => true,
+        }
+    }
+
+    /// The key of the bottom bar hint under a column, e.g. `j` for a click
+    /// on `j/k:move`, read from what the screen shows.
+    fn footer_hint(&self, row: u16, column: u16) -> Option<KeyChord> {
+        let line = self.renderer.shown_row(row as usize)?;
+        let column = column as usize;
+        let mut start = 0;
+        for hint in line.split(" | ") {
+            let end = start + hint.chars().count();
+            if (start..end).contains(&column) {
+                let (keys, _) = hint.trim().rsplit_once(':')?;
+                // `/` is a key of its own, not a separator
+                let key = if keys.starts_with('/') { "/" } else { keys.split('/').next()? };
+                return KeyChord::parse(key).ok();
+            }
+            start = end + 3;
+        }
+        None
+    }
+
+    fn over_pane(&self, row: u16, size: (u16, u16)) -> bool {
+        // The pane's title line belongs to it
+        self.pane_visible() && Self::pane_area(size).is_some_and(|(_, pane_y, _, _)| row + 1 >= pane_y)
+    }
+
+    /// Selects the entry under a click, or launches it when it was clicked
+    /// just before. Clicks on the embedded program focus it.
+    fn click_entry(&mut self, column: u16, row: u16, size: (u16, u16)) -> io::Result<()> {
+        if self.over_pane(row, size) {
+            self.focus = Focus::Pane;
+            return Ok(());
+        }
+        let list_width = size.0 - self.sidebar_width(size.0);
+        let index = match row.checked_sub(LIST_TOP) {
+            Some(offset) if (offset as usize) < self.list_rows(size) && column < list_width => {
+                self.list_offset + offset as usize
+            }
+            _ => return Ok(()),
+        };
+        if index >= self.config.get_programs().len() {
+            return Ok(());
         }
 
+        self.focus = Focus::List;
+        self.selected_index = index;
+        let double = self.last_click.is_some_and(|(at, clicked)| clicked == index && at.elapsed() <= DOUBLE_CLICK_INTERVAL);
+        if double {
+            self.last_click = None;
+            self.launch_selected_program()?;
+        } else {
+            self.last_click = Some((Instant::now(), index));
+        }
         Ok(())
     }
 
     fn handle_normal_mode(&mut self, key: KeyEvent) -> io::Result<bool> {
-        match key.code {
-            KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
-            KeyCode::Char('j') | KeyCode::Down => {
-                let programs = self.config.get_programs();
-                if !programs.is_empty() {
-                    self.selected_index = (self.selected_index + 1) % programs.len();
+        let chord = KeyChord::from_event(key);
+        if self.leader_pending {
+            self.leader_pending = false;
+            if key.code != KeyCode::Esc && !self.launch_hotkey(Hotkey::Leader(chord))? {
+                self.status_message = Some(format!("No hotkey {}", self.keymap.hotkey_label(Hotkey::Leader(chord))));
+            }
+            return Ok(false);
+        }
+        // Hotkeys only start a sequence, so `5` after a count stays a digit
+        if self.keys.pending().is_empty() {
+            if chord == self.keymap.leader() {
+                self.leader_pending = true;
+                return Ok(false);
+            }
+            if self.launch_hotkey(Hotkey::Key(chord))? {
+                return Ok(false);
+            }
+        }
+
+        let (key, count) = match self.keys.feed(key) {
+            Parsed::Pending | Parsed::Cleared => return Ok(false),
+            Parsed::Motion(motion) => {
+                self.apply_motion(motion)?;
+                return Ok(false);
+            }
+            Parsed::Key(key, count) => (key, count),
+        };
+        let Some(action) = self.keymap.action(KeyMode::Normal, key) else {
+            return Ok(false);
+        };
+        let n = count.unwrap_or(1);
+
+        match action {
+            Action::Quit if self.has_selection() => self.clear_selection(),
+            Action::Quit => return Ok(true),
+            Action::Launch if self.has_selection() => self.open_selection_menu(None),
+            Action::Delete if self.has_selection() => self.open_selection_menu(Some(SelectionAction::Delete)),
+            Action::ToggleSelect => {
+                if let Some(name) = self.selected_name() {
+                    if !self.selection.remove(&name) {
+                        self.selection.insert(name);
+                    }
                 }
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                let programs = self.config.get_programs();
-                if !programs.is_empty() {
-                    self.selected_index =
-                        if self.selected_index == 0 { programs.len() - 1 } else { self.selected_index - 1 };
+            Action::Visual => match self.visual_anchor {
+                Some(_) => {
+                    let names: Vec<String> = self.selected_programs().into_iter().map(|p| p.name).collect();
+                    self.visual_anchor = None;
+                    self.selection.extend(names);
                 }
+                None => self.visual_anchor = self.selected_name(),
+            },
+            Action::Down => self.apply_motion(Motion::Down(n))?,
+            Action::Up => self.apply_motion(Motion::Up(n))?,
+            Action::Last => self.apply_motion(count.map_or(Motion::Last, Motion::Line))?,
+            Action::ScreenTop => self.apply_motion(Motion::ScreenTop)?,
+            Action::ScreenMiddle => self.apply_motion(Motion::ScreenMiddle)?,
+            Action::ScreenBottom => self.apply_motion(Motion::ScreenBottom)?,
+            Action::JumpBack => self.apply_motion(Motion::JumpBack(n))?,
+            Action::JumpForward => self.apply_motion(Motion::JumpForward(n))?,
+            Action::Cancel => {
+                self.cancel_job();
             }
-            KeyCode::Enter => {
+            Action::FocusPane => {
+                if self.pane.is_some() {
+                    self.pane_hidden = false;
+                    self.focus = Focus::Pane;
+                } else {
+                    self.status_message = Some("No embedded program running".to_string());
+                }
+            }
+            Action::TogglePane => {
+                self.toggle_pane();
+            }
+            Action::Progress => {
+                if self.job.is_some() {
+                    self.mode = Mode::Progress;
+                } else if self.batch.is_some() {
+                    self.mode = Mode::Batch;
+                } else {
+                    self.status_message = Some("No running program".to_string());
+                }
+            }
+            Action::Launch => {
                 self.launch_selected_program()?;
             }
-            KeyCode::Char('a') => {
+            Action::Add => {
                 self.mode = Mode::AddProgram;
                 self.add_form.reset();
             }
-            KeyCode::Char('d') => {
+            Action::Delete => {
                 self.delete_selected_program()?;
             }
-            KeyCode::Char('h') => {
+            Action::Help => {
                 self.mode = Mode::Help;
             }
-            KeyCode::Char('r') => {
+            Action::Reload => {
                 self.reload_config()?;
             }
+            Action::Container => {
+                if let Some(program) = self.config.get_programs().get(self.selected_index).map(|p| (*p).clone()) {
+                    self.open_container_picker(program);
+                }
+            }
+            Action::Logs => {
+                if let Some(program) = self.config.get_programs().get(self.selected_index).map(|p| (*p).clone()) {
+                    match &program.logs {
+                        Some(source) => {
+                            let host = self.targets(&program).into_iter().next();
+                            self.open_logs(program.display_name.clone(), source, host.as_ref());
+                        }
+                        None => self.status_message = Some(format!("No logs configured for {}", program.display_name)),
+                    }
+                }
+            }
+            Action::Sudo => {
+                self.authenticate_sudo(None);
+            }
+            Action::CommandLine => {
+                self.command_line.open();
+                self.mode = Mode::Command;
+            }
+            Action::Systemd => {
+                // Units of another host must not show while these load
+                self.units.clear();
+                self.refresh_units();
+                self.unit_index = 0;
+                self.mode = Mode::Systemd;
+            }
+            Action::Host => {
+                let hosts = self.host_choices();
+                self.host_index = hosts.iter().position(|h| *h == self.active_host).unwrap_or(0);
+                self.mode = Mode::HostSwitcher;
+            }
             _ => {}
         }
         Ok(false)
     }
 
+    fn has_selection(&self) -> bool {
+        !self.selection.is_empty() || self.visual_anchor.is_some()
+    }
+
+    fn clear_selection(&mut self) {
+        self.selection.clear();
+        self.visual_anchor = None;
+    }
+
+    /// The selected entries in list order: those picked one by one and,
+    /// in visual mode, the range from where it started to the cursor.
+    fn selected_programs(&self) -> Vec<ProgramEntry> {
+        let range = self.visual_anchor.as_ref().and_then(|name| self.index_of(name)).map(|anchor| {
+            anchor.min(self.selected_index)..=anchor.max(self.selected_index)
+        });
+        self.config
+            .get_programs()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, p)| self.selection.contains(&p.name) || range.as_ref().is_some_and(|r| r.contains(i)))
+            .map(|(_, p)| p.clone())
+            .collect()
+    }
+
+    /// Opens the batch actions for the selection, or goes straight to the
+    /// input one of them needs.
+    fn open_selection_menu(&mut self, prompt: Option<SelectionAction>) {
+        self.selection_menu = SelectionMenu {
+            names: self.selected_programs().into_iter().map(|p| p.name).collect(),
+            index: 0,
+            prompt: prompt.map(|action| (action, String::new())),
+        };
+        self.mode = Mode::Selection;
+    }
+
+    /// Selects and launches the entry with this hotkey, if there is one.
+    fn launch_hotkey(&mut self, hotkey: Hotkey) -> io::Result<bool> {
+        let programs = self.config.get_programs();
+        let Some(index) = programs.iter().position(|p| self.config.hotkey(p) == Some(hotkey)) else {
+            return Ok(false);
+        };
+        self.selected_index = index;
+        self.launch_selected_program()?;
+        Ok(true)
+    }
+
+    fn apply_motion(&mut self, motion: Motion) -> io::Result<()> {
+        let count = self.config.get_programs().len();
+        if count == 0 {
+            return Ok(());
+        }
+        let last = count - 1;
+        let shown_range = || terminal::size().map(|size| self.shown_range(size));
+
+        let target = match motion {
+            // A single step wraps around the list; counted ones stop at its ends
+            Motion::Down(1) => (self.selected_index + 1) % count,
+            Motion::Up(1) => self.selected_index.checked_sub(1).unwrap_or(last),
+            // Counts saturate, so they can be as large as usize itself
+            Motion::Down(n) => self.selected_index.saturating_add(n).min(last),
+            Motion::Up(n) => self.selected_index.saturating_sub(n),
+            Motion::Line(line) => line.clamp(1, count) - 1,
+            Motion::First => 0,
+            Motion::Last => last,
+            Motion::ScreenTop => shown_range()?.0,
+            Motion::ScreenMiddle => {
+                let (first_shown, last_shown) = shown_range()?;
+                (first_shown + last_shown) / 2
+            }
+            Motion::ScreenBottom => shown_range()?.1,
+            Motion::SetMark(mark) => {
+                if let Some(program) = self.config.get_programs().get(self.selected_index) {
+                    self.marks.insert(mark, program.name.clone());
+                    self.status_message = Some(format!("Mark '{}' set on {}", mark, program.display_name));
+                }
+                return Ok(());
+            }
+            Motion::JumpToMark(mark) => {
+                let target = self.marks.get(&mark).and_then(|name| self.index_of(name));
+                match target {
+                    Some(index) => index,
+                    None => {
+                        self.status_message = Some(format!("Mark not set: {}", mark));
+                        return Ok(());
+                    }
+                }
+            }
+            Motion::JumpBack(n) => {
+                self.jump_back(n);
+                return Ok(());
+            }
+            Motion::JumpForward(n) => {
+                self.jump_forward(n);
+                return Ok(());
+            }
+        };
+
+        if motion.is_jump() && target != self.selected_index {
+            self.record_jump();
+        }
+        self.selected_index = target;
+        Ok(())
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.config.get_programs().iter().position(|p| p.name == name)
+    }
+
+    fn selected_name(&self) -> Option<String> {
+        self.config.get_programs().get(self.selected_index).map(|p| p.name.clone())
+    }
+
+    /// Remembers the selected entry before a jump. Jumping from the middle
+    /// of the list forgets the newer positions, as in a browser history.
+    fn record_jump(&mut self) {
+        let Some(name) = self.selected_name() else { return };
+        self.jumps.truncate(self.jump_index);
+        if self.jumps.last() != Some(&name) {
+            self.jumps.push(name);
+        }
+        self.jump_index = self.jumps.len();
+    }
+
+    fn jump_back(&mut self, n: usize) {
+        if self.jump_index == self.jumps.len() {
+            // Keep where we are so Ctrl-I can come back to it
+            if let Some(name) = self.selected_name() {
+                if self.jumps.last() != Some(&name) {
+                    self.jumps.push(name);
+                }
+            }
+            self.jump_index = self.jumps.len().saturating_sub(1);
+        }
+        match self.jump_index.checked_sub(n) {
+            Some(index) => self.jump_to(index),
+            None => self.status_message = Some("At the start of the jump list".to_string()),
+        }
+    }
+
+    fn jump_forward(&mut self, n: usize) {
+        let index = self.jump_index.saturating_add(n);
+        if index < self.jumps.len() {
+            self.jump_to(index);
+        } else {
+            self.status_message = Some("At the end of the jump list".to_string());
+        }
+    }
+
+    fn jump_to(&mut self, index: usize) {
+        self.jump_index = index;
+        match self.index_of(&self.jumps[index]) {
+            Some(selected) => self.selected_index = selected,
+            None => self.status_message = Some(format!("No such program: {}", self.jumps[index])),
+        }
+    }
+
+    fn handle_pane_mode(&mut self, key: KeyEvent) -> io::Result<()> {
+        if self.keymap.action(KeyMode::Normal, key) == Some(Action::FocusPane) {
+            self.focus = Focus::List;
+        } else if let Some((_, pane)) = self.pane.as_mut() {
+            pane.send_key(key)?;
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::collapsible_if, clippy::collapsible_match)]
     fn handle_add_program_mode(&mut self, key: KeyEvent) -> io::Result<()> {
         match key.code {
             KeyCode::Esc => {
-                self.mode = Mode::Normal;
+                self.return_to_normal();
                 self.add_form.reset();
             }
             KeyCode::Enter => {
@@ -207,7 +1124,7 @@ impl Dashboard {
                         if self.add_form.step == 7 {
                             // Review step - save the program
                             self.save_new_program()?;
-                            self.mode = Mode::Normal;
+                            self.return_to_normal();
                         }
                     }
                 }
@@ -239,498 +1156,3046 @@ impl Dashboard {
     }
 
     fn handle_help_mode(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('h') => {
-                self.mode = Mode::Normal;
-            }
-            _ => {}
+        if self.keymap.action(KeyMode::Help, key) == Some(Action::Close) {
+            self.return_to_normal();
         }
     }
 
-    fn handle_show_output_mode(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Esc | KeyCode::Char(' ') | KeyCode::Char('q') => {
-                self.mode = Mode::Normal;
-                self.output_data = None;
-            }
-            _ => {}
-        }
-    }
+    fn handle_show_output_mode(&mut self, key: KeyEvent) -> io::Result<()> {
+        let rows = Self::output_rows(terminal::size()?);
+        let Some(view) = self.output_view.as_mut() else {
+            self.mode = Mode::Normal;
+            return Ok(());
+        };
+        view.message = None;
 
-    fn launch_selected_program(&mut self) -> io::Result<()> {
-        let programs = self.config.get_programs();
-        if let Some(program) = programs.get(self.selected_index) {
-            if program.show_output {
-                // Capture output
-                let output = if program.run_with_sudo {
-                    let mut cmd = Command::new("sudo");
-                    cmd.arg(&program.command);
-                    if !program.args.is_empty() {
-                        cmd.args(&program.args);
+        if let Some((kind, input)) = view.prompt.as_mut() {
+            match key.code {
+                KeyCode::Esc => view.prompt = None,
+                KeyCode::Backspace if input.is_empty() => view.prompt = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter if *kind == '/' => {
+                    let pattern = std::mem::take(input);
+                    view.prompt = None;
+                    view.search(&pattern, rows);
+                }
+                KeyCode::Enter => {
+                    let line = std::mem::take(input);
+                    view.prompt = None;
+                    match command::parse(&line) {
+                        Ok(ExCommand::Write(Some(path), overwrite)) => view.message = Some(view.save(&path, overwrite)),
+                        Ok(ExCommand::Write(None, _)) => view.message = Some("Argument required: w <file>".to_string()),
+                        Ok(ExCommand::Quit) => self.close_output(),
+                        Ok(_) => view.message = Some(format!("Not available in the output viewer: {}", line.trim())),
+                        Err(e) => view.message = Some(e),
                     }
-                    cmd.output()
-                } else {
-                    let mut cmd = Command::new(&program.command);
-                    if !program.args.is_empty() {
-                        cmd.args(&program.args);
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match self.keymap.action(KeyMode::Output, key) {
+            Some(Action::Close) => self.close_output(),
+            Some(Action::Down) => view.scroll_to(view.top + 1, rows),
+            Some(Action::Up) => view.top = view.top.saturating_sub(1),
+            Some(Action::Last) => view.top = view.max_top(rows),
+            Some(Action::Search) => view.prompt = Some(('/', String::new())),
+            Some(Action::NextMatch) => view.find(true, rows),
+            Some(Action::PreviousMatch) => view.find(false, rows),
+            Some(Action::Yank) => {
+                let (what, text) = view.selection();
+                view.message = Some(match clipboard::copy(&text) {
+                    Ok(()) => format!("Copied {} to the clipboard", what),
+                    Err(e) if e.kind() == io::ErrorKind::InvalidInput => format!("Cannot copy {}: {}", what, e),
+                    Err(e) => return Err(e),
+                });
+            }
+            Some(Action::CommandLine) => view.prompt = Some((':', String::new())),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn close_output(&mut self) {
+        self.mode = std::mem::replace(&mut self.output_return, Mode::Normal);
+        self.output_view = None;
+    }
+
+    /// Lines of output that fit in the text box on a screen of this size.
+    fn output_rows((_, height): (u16, u16)) -> usize {
+        height.saturating_sub(6).max(1) as usize
+    }
+
+    fn handle_progress_mode(&mut self, key: KeyEvent) {
+        match self.keymap.action(KeyMode::Progress, key) {
+            Some(Action::Cancel) => {
+                self.cancel_job();
+            }
+            Some(Action::Close) => {
+                self.mode = Mode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_batch_mode(&mut self, key: KeyEvent) {
+        let Some((display_name, batch)) = &self.batch else {
+            self.mode = Mode::Normal;
+            return;
+        };
+        let count = batch.items().len();
+
+        match self.keymap.action(KeyMode::Batch, key) {
+            Some(Action::Cancel) => {
+                self.cancel_batch();
+            }
+            Some(Action::Down) if count > 0 => {
+                self.batch_index = (self.batch_index + 1) % count;
+            }
+            Some(Action::Up) if count > 0 => {
+                self.batch_index = if self.batch_index == 0 { count - 1 } else { self.batch_index - 1 };
+            }
+            Some(Action::Open) => {
+                if let Some(item) = batch.items().get(self.batch_index) {
+                    if matches!(item.state, StepState::Pending | StepState::Running) {
+                        self.status_message = Some(format!("Still running on {}", item.name));
+                    } else {
+                        let title = format!("{} @ {}", display_name, item.name);
+                        self.output_view = Some(OutputView::new(title, &item.output));
+                        self.output_return = Mode::Batch;
+                        self.mode = Mode::ShowOutput;
+                    }
+                }
+            }
+            Some(Action::Close) => {
+                // A finished batch is dismissed; a running one keeps going
+                if batch.is_finished() {
+                    self.batch = None;
+                }
+                self.mode = Mode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_host_switcher_mode(&mut self, key: KeyEvent) {
+        let hosts = self.host_choices();
+        match self.keymap.action(KeyMode::Picker, key) {
+            Some(Action::Close) => {
+                self.mode = Mode::Normal;
+            }
+            Some(Action::Down) => {
+                self.host_index = (self.host_index + 1) % hosts.len();
+            }
+            Some(Action::Up) => {
+                self.host_index = if self.host_index == 0 { hosts.len() - 1 } else { self.host_index - 1 };
+            }
+            Some(Action::Select) => {
+                self.active_host = hosts[self.host_index].clone();
+                self.status_message = Some(match &self.active_host {
+                    Some(host) => format!("Active host: {}", host),
+                    None => "Running locally".to_string(),
+                });
+                self.mode = Mode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_container_picker_mode(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some((_, containers)) = &self.container_pick else {
+            self.mode = Mode::Normal;
+            return Ok(());
+        };
+        match self.keymap.action(KeyMode::Picker, key) {
+            Some(Action::Close) => {
+                self.container_pick = None;
+                self.mode = Mode::Normal;
+            }
+            Some(Action::Down) => {
+                self.container_index = (self.container_index + 1) % containers.len();
+            }
+            Some(Action::Up) => {
+                self.container_index =
+                    if self.container_index == 0 { containers.len() - 1 } else { self.container_index - 1 };
+            }
+            Some(Action::Select) => {
+                let (mut program, containers) = self.container_pick.take().unwrap();
+                program.container = Some(containers[self.container_index].name.clone());
+                self.mode = Mode::Normal;
+                self.launch_entry(program)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_systemd_mode(&mut self, key: KeyEvent) -> io::Result<()> {
+        let count = self.visible_units().len();
+        let action = match self.keymap.action(KeyMode::Systemd, key) {
+            Some(Action::Close) => {
+                self.mode = Mode::Normal;
+                return Ok(());
+            }
+            Some(Action::Down) if count > 0 => {
+                self.unit_index = (self.unit_index + 1) % count;
+                return Ok(());
+            }
+            Some(Action::Up) if count > 0 => {
+                self.unit_index = if self.unit_index == 0 { count - 1 } else { self.unit_index - 1 };
+                return Ok(());
+            }
+            Some(Action::FailedOnly) => {
+                self.failed_units_only = !self.failed_units_only;
+                self.unit_index = 0;
+                return Ok(());
+            }
+            Some(Action::Refresh) => {
+                self.refresh_units();
+                return Ok(());
+            }
+            Some(Action::Pin) => {
+                self.pin_selected_unit();
+                return Ok(());
+            }
+            Some(Action::Logs) => {
+                if let Some(unit) = self.visible_units().get(self.unit_index).map(|u| u.name.clone()) {
+                    let host = self.active_host.as_deref().map(|h| self.config.resolve_host(h));
+                    self.open_logs(unit.clone(), &LogSource::Unit(unit), host.as_ref());
+                }
+                return Ok(());
+            }
+            Some(Action::Status) => systemd::Action::Status,
+            Some(Action::Start) => systemd::Action::Start,
+            Some(Action::Stop) => systemd::Action::Stop,
+            Some(Action::Restart) => systemd::Action::Restart,
+            _ => return Ok(()),
+        };
+
+        let Some(unit) = self.visible_units().get(self.unit_index).map(|u| (*u).clone()) else {
+            return Ok(());
+        };
+        self.launch_entry(systemd::entry(&unit, action))?;
+        if action.changes_state() {
+            // These ran in the terminal and have finished; show their effect
+            self.refresh_units();
+        }
+        Ok(())
+    }
+
+    fn handle_logs_mode(&mut self, key: KeyEvent) {
+        let Some(view) = self.log_view.as_mut() else {
+            self.mode = Mode::Normal;
+            return;
+        };
+
+        if let Some(input) = view.filter_input.as_mut() {
+            match key.code {
+                KeyCode::Esc => {
+                    view.filter_input = None;
+                    view.filter_error = None;
+                }
+                KeyCode::Enter if input.is_empty() => {
+                    view.filter = None;
+                    view.filter_input = None;
+                    view.scroll = 0;
+                }
+                KeyCode::Enter => match Regex::new(input) {
+                    Ok(filter) => {
+                        view.filter = Some(filter);
+                        view.filter_input = None;
+                        view.filter_error = None;
+                        view.scroll = 0;
                     }
-                    cmd.output()
+                    Err(e) => view.filter_error = Some(e.to_string().lines().last().unwrap_or_default().to_string()),
+                },
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return;
+        }
+
+        match self.keymap.action(KeyMode::Logs, key) {
+            Some(Action::Close) => {
+                let view = self.log_view.take().unwrap();
+                self.mode = view.return_to;
+            }
+            Some(Action::Pause) => {
+                view.paused_at = match view.paused_at {
+                    Some(_) => None,
+                    None => Some(view.tail.lines().total()),
                 };
+                view.scroll = 0;
+            }
+            Some(Action::Up) => {
+                // Scrolling back pauses so new lines do not move the view
+                let total = view.tail.lines().total();
+                view.paused_at.get_or_insert(total);
+                view.scroll = (view.scroll + 1).min(total);
+            }
+            Some(Action::Down) => {
+                view.scroll = view.scroll.saturating_sub(1);
+            }
+            Some(Action::Follow) => {
+                view.paused_at = None;
+                view.scroll = 0;
+            }
+            Some(Action::Filter) => {
+                view.filter_input = Some(view.filter.as_ref().map_or_else(String::new, |f| f.as_str().to_string()));
+            }
+            _ => {}
+        }
+    }
 
-                match output {
-                    Ok(output) => {
-                        let stdout = String::from_utf8_lossy(&output.stdout);
-                        let stderr = String::from_utf8_lossy(&output.stderr);
-                        let combined_output = if stderr.is_empty() {
-                            stdout.to_string()
-                        } else if stdout.is_empty() {
-                            stderr.to_string()
-                        } else {
-                            format!("STDOUT:\n{}\n\nSTDERR:\n{}", stdout, stderr)
-                        };
-                        
-                        self.output_data = Some((program.display_name.clone(), combined_output));
-                        self.mode = Mode::ShowOutput;
-                        
-                        if output.status.success() {
-                            self.status_message = Some(format!("Executed: {}", program.display_name));
-                        } else {
-                            self.status_message = Some(format!("Executed with errors: {}", program.display_name));
-                        }
+    fn handle_selection_mode(&mut self, key: KeyEvent) -> io::Result<()> {
+        if let Some((action, input)) = self.selection_menu.prompt.as_mut() {
+            let action = *action;
+            if action == SelectionAction::Delete {
+                self.selection_menu.prompt = None;
+                if self.keymap.action(KeyMode::Picker, key) == Some(Action::Confirm) {
+                    self.delete_selection();
+                } else {
+                    self.status_message = Some("Nothing deleted".to_string());
+                    self.mode = Mode::Normal;
+                }
+                return Ok(());
+            }
+
+            match key.code {
+                KeyCode::Esc => {
+                    self.selection_menu.prompt = None;
+                }
+                KeyCode::Enter => {
+                    let input = input.trim().to_string();
+                    self.selection_menu.prompt = None;
+                    match action {
+                        SelectionAction::Group => self.group_selection(input),
+                        _ => self.export_selection(&input),
                     }
-                    Err(e) => {
-                        self.status_message = Some(format!("Error launching {}: {}", program.display_name, e));
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        let count = SelectionAction::ALL.len();
+        match self.keymap.action(KeyMode::Picker, key) {
+            Some(Action::Close) => {
+                self.mode = Mode::Normal;
+            }
+            Some(Action::Down) => {
+                self.selection_menu.index = (self.selection_menu.index + 1) % count;
+            }
+            Some(Action::Up) => {
+                self.selection_menu.index = (self.selection_menu.index + count - 1) % count;
+            }
+            Some(Action::Select) => match SelectionAction::ALL[self.selection_menu.index] {
+                SelectionAction::LaunchInOrder => self.launch_selection(true),
+                SelectionAction::LaunchTogether => self.launch_selection(false),
+                action @ (SelectionAction::Delete | SelectionAction::Group) => {
+                    self.selection_menu.prompt = Some((action, String::new()));
+                }
+                SelectionAction::Export => {
+                    self.selection_menu.prompt = Some((SelectionAction::Export, DEFAULT_EXPORT_PATH.to_string()));
+                }
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The entries the batch actions menu was opened for.
+    fn menu_programs(&self) -> Vec<ProgramEntry> {
+        self.selection_menu.names.iter().filter_map(|name| self.config.programs.get(name).cloned()).collect()
+    }
+
+    /// Leaves the batch actions once one has been done.
+    fn finish_selection(&mut self, message: String) {
+        self.clear_selection();
+        self.status_message = Some(message);
+        self.mode = Mode::Normal;
+    }
+
+    /// Runs the selected entries captured: one after another as a single
+    /// job, stopping at the first failure, or all at once as a batch.
+    fn launch_selection(&mut self, in_order: bool) {
+        let programs = self.menu_programs();
+        if let Some(program) = programs.iter().find(|p| p.picks_container()) {
+            self.status_message = Some(format!("{} picks its container at launch; launch it on its own", program.display_name));
+            return;
+        }
+        if !in_order {
+            if let Some(program) = programs.iter().find(|p| !p.depends_on.is_empty()) {
+                self.status_message = Some(format!("{} has prerequisites; launch one after another", program.display_name));
+                return;
+            }
+        }
+        // Captured runs cannot show sudo's prompt; the selection stays for another try
+        if programs.iter().any(|p| self.uses_local_sudo(p)) && !self.authenticate_sudo(None) {
+            return;
+        }
+
+        let display_name = format!("{} programs", programs.len());
+        if in_order {
+            if let Some((running, _, _)) = &self.job {
+                self.status_message = Some(format!("Already running: {}", running));
+                return;
+            }
+            // A prerequisite shared by several programs, or selected itself,
+            // runs once, before the first program that needs it
+            let mut steps = Vec::new();
+            let mut added = HashSet::new();
+            for program in &programs {
+                for prerequisite in self.config.prerequisites(program) {
+                    if added.insert(prerequisite.name.clone()) {
+                        steps.extend(self.steps_as_prerequisite(prerequisite));
                     }
                 }
-            } else {
-                // Regular execution without capturing output
-                // Save current terminal state
-                execute!(io::stdout(), LeaveAlternateScreen, Show)?;
-                terminal::disable_raw_mode()?;
-
-                let result = if program.run_with_sudo {
-                    // Handle sudo execution
-                    let mut cmd = Command::new("sudo");
-                    cmd.arg(&program.command);
-                    if !program.args.is_empty() {
-                        cmd.args(&program.args);
+                if added.insert(program.name.clone()) {
+                    steps.extend(self.entry_steps(program, false));
+                }
+            }
+            match Workflow::start(steps) {
+                Ok(workflow) => {
+                    let names = programs.iter().map(|program| program.name.clone()).collect();
+                    self.job = Some((display_name, names, workflow));
+                    self.pending_launch = None;
+                    self.clear_selection();
+                    self.status_message = None;
+                    self.mode = Mode::Progress;
+                }
+                Err(e) => {
+                    self.status_message = Some(format!("Error launching {}: {}", display_name, e));
+                }
+            }
+            return;
+        }
+
+        if let Some((running, batch)) = &self.batch {
+            if !batch.is_finished() {
+                self.status_message = Some(format!("Already running: {}", running));
+                return;
+            }
+        }
+        let mut items = Vec::new();
+        for program in &programs {
+            let script = self.remote_script(program);
+            let multi_host = self.is_multi_host(program);
+            for host in self.run_targets(program) {
+                let (name, detail, command) = match host {
+                    Some(host) => {
+                        let name = if multi_host {
+                            format!("{} @ {}", program.display_name, host.alias)
+                        } else {
+                            program.display_name.clone()
+                        };
+                        (name, host.address(), ssh::command(&host, &script, false))
                     }
-                    cmd.status()
-                } else {
-                    // Regular execution
-                    let mut cmd = Command::new(&program.command);
-                    if !program.args.is_empty() {
-                        cmd.args(&program.args);
+                    None => {
+                        let mut command = Command::new("sh");
+                        command.args(["-c", &script]);
+                        (program.display_name.clone(), "local".to_string(), command)
                     }
-                    cmd.status()
                 };
+                items.push(BatchItem::new(name, detail, command, self.config.timeout_for(program)));
+            }
+        }
+
+        let limit = items.len();
+        self.clear_selection();
+        self.status_message = None;
+        self.batch = Some((display_name, Batch::start(items, limit)));
+        self.batch_kind = BatchKind::Programs;
+        self.batch_index = 0;
+        self.mode = Mode::Batch;
+    }
+
+    fn delete_selection(&mut self) {
+        let names = self.selection_menu.names.clone();
+        for name in &names {
+            let outside: Vec<_> = self
+                .config
+                .dependents(name)
+                .into_iter()
+                .filter(|p| !names.contains(&p.name))
+                .map(|p| p.display_name.as_str())
+                .collect();
+            if !outside.is_empty() {
+                let message = format!("Cannot delete {}: required by {}", name, outside.join(", "));
+                self.status_message = Some(message);
+                self.mode = Mode::Normal;
+                return;
+            }
+        }
+
+        for name in &names {
+            self.config.remove_program(name);
+        }
+        let count = self.config.get_programs().len();
+        self.selected_index = self.selected_index.min(count.saturating_sub(1));
+        match self.config.save() {
+            Ok(()) => self.finish_selection(format!("Deleted {} programs", names.len())),
+            Err(e) => self.finish_selection(format!("Error saving config: {}", e)),
+        }
+    }
+
+    /// Sets the group of the selected entries; an empty name removes it.
+    fn group_selection(&mut self, group: String) {
+        let names = self.selection_menu.names.clone();
+        for name in &names {
+            if let Some(program) = self.config.programs.get_mut(name) {
+                program.group = (!group.is_empty()).then(|| group.clone());
+            }
+        }
+        let message = match self.config.save() {
+            Err(e) => format!("Error saving config: {}", e),
+            Ok(()) if group.is_empty() => format!("Removed {} programs from their group", names.len()),
+            Ok(()) => format!("Moved {} programs to {}", names.len(), group),
+        };
+        self.finish_selection(message);
+    }
+
+    fn export_selection(&mut self, path: &str) {
+        if path.is_empty() {
+            return;
+        }
+        let path = expand_home(path);
+        let names: Vec<&str> = self.selection_menu.names.iter().map(String::as_str).collect();
+        let message = match self.config.export(&names, &path) {
+            Ok(()) => format!("Exported {} programs to {}", names.len(), path.display()),
+            Err(e) => format!("Error exporting to {}: {}", path.display(), e),
+        };
+        self.finish_selection(message);
+    }
+
+    /// Edits the command line; returns true when a command asks to quit.
+    fn handle_command_mode(&mut self, key: KeyEvent) -> io::Result<bool> {
+        if !matches!(key.code, KeyCode::Tab | KeyCode::BackTab) {
+            self.command_line.clear_completions();
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Backspace if self.command_line.input.is_empty() => {
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Backspace => {
+                self.command_line.input.pop();
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                let names: Vec<&str> = self.config.get_programs().iter().map(|p| p.name.as_str()).collect();
+                self.command_line.complete(&names, key.code == KeyCode::Tab);
+            }
+            KeyCode::Up => self.command_line.recall(true),
+            KeyCode::Down => self.command_line.recall(false),
+            KeyCode::Enter => {
+                let line = self.command_line.submit();
+                self.mode = Mode::Normal;
+                match command::parse(&line) {
+                    Ok(command) => return self.execute_command(command),
+                    Err(e) => self.status_message = Some(e),
+                }
+            }
+            KeyCode::Char(c) => self.command_line.input.push(c),
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Runs a `:` command; returns true when the dashboard should quit.
+    fn execute_command(&mut self, command: ExCommand) -> io::Result<bool> {
+        match command {
+            ExCommand::Run(name) => {
+                if self.select_program(&name) {
+                    self.launch_selected_program()?;
+                }
+            }
+            ExCommand::Add => {
+                self.mode = Mode::AddProgram;
+                self.add_form.reset();
+            }
+            ExCommand::Delete(name) => {
+                if self.select_program(&name) {
+                    self.delete_selected_program()?;
+                }
+            }
+            ExCommand::Set(option, value) => self.set_option(&option, &value),
+            ExCommand::Reload => self.reload_config()?,
+            ExCommand::Write(None, _) => {
+                self.write_config();
+            }
+            ExCommand::Write(Some(_), _) => {
+                self.status_message = Some("Only captured output is saved to a file; use :w <file> in the output viewer".to_string());
+            }
+            ExCommand::Quit => return Ok(true),
+            ExCommand::WriteQuit => return Ok(self.write_config()),
+            ExCommand::Help => self.mode = Mode::Help,
+        }
+        Ok(false)
+    }
+
+    /// Moves the selection to the entry with the given name, or display
+    /// name, reporting unknown names in the status line.
+    fn select_program(&mut self, name: &str) -> bool {
+        let programs = self.config.get_programs();
+        let found = programs
+            .iter()
+            .position(|p| p.name == name)
+            .or_else(|| programs.iter().position(|p| p.display_name.eq_ignore_ascii_case(name)));
+        match found {
+            Some(index) => {
+                self.selected_index = index;
+                true
+            }
+            None => {
+                self.status_message = Some(format!("No such program: {}", name));
+                false
+            }
+        }
+    }
+
+    fn set_option(&mut self, option: &str, value: &str) {
+        let selected = self.config.get_programs().get(self.selected_index).map(|p| p.name.clone());
+        self.config.sort = match (option, value) {
+            ("sort", "name") => SortOrder::Name,
+            ("sort", "usage") => SortOrder::Usage,
+            ("sort", _) => {
+                self.status_message = Some(format!("Invalid value for sort: {} (name or usage)", value));
+                return;
+            }
+            _ => {
+                self.status_message = Some(format!("Unknown option: {}", option));
+                return;
+            }
+        };
+        // Keep the same entry selected in the new order
+        if let Some(name) = selected {
+            self.select_program(&name);
+        }
+        self.status_message = Some(format!("{}={} (:w to save)", option, value));
+    }
+
+    /// Saves the configuration; returns false and reports the error if
+    /// that fails.
+    fn write_config(&mut self) -> bool {
+        match self.config.save() {
+            Ok(()) => {
+                self.status_message = Some(format!("Written: {}", Config::config_file().display()));
+                true
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Error saving config: {}", e));
+                false
+            }
+        }
+    }
+
+    fn handle_sudo_prompt_mode(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(prompt) = self.sudo_prompt.as_mut() else {
+            self.mode = Mode::Normal;
+            return Ok(());
+        };
+
+        match key.code {
+            KeyCode::Esc => {
+                self.sudo_prompt = None;
+                self.status_message = Some("sudo authentication cancelled".to_string());
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Enter => {
+                let authenticated = sudo::authenticate(&prompt.password);
+                prompt.password.clear();
+                match authenticated {
+                    Ok(true) => {
+                        let prompt = self.sudo_prompt.take().unwrap();
+                        self.sudo_cached_at = Some(Instant::now());
+                        self.mode = Mode::Normal;
+                        match prompt.then {
+                            Some(program) => return self.launch_entry(program),
+                            None => self.status_message = Some("sudo credentials cached".to_string()),
+                        }
+                    }
+                    Ok(false) => prompt.error = Some("Sorry, try again.".to_string()),
+                    Err(e) => prompt.error = Some(format!("Cannot run sudo: {}", e)),
+                }
+            }
+            KeyCode::Backspace => {
+                prompt.password.pop();
+            }
+            KeyCode::Char(c) => prompt.password.push(c),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Makes sure sudo will not ask for a password, asking the user for it
+    /// first if needed. Returns true when sudo can be used right away;
+    /// otherwise `then` is launched once the password has been entered.
+    fn authenticate_sudo(&mut self, then: Option<ProgramEntry>) -> bool {
+        if sudo::is_cached() {
+            self.sudo_cached_at = Some(Instant::now());
+            if then.is_none() {
+                self.status_message = Some("sudo credentials cached".to_string());
+            }
+            return true;
+        }
+
+        self.sudo_prompt = Some(SudoPrompt {
+            password: String::new(),
+            error: None,
+            then,
+        });
+        self.mode = Mode::SudoPrompt;
+        false
+    }
+
+    /// Whether any captured command of an entry or its prerequisites runs
+    /// through sudo on this machine.
+    fn uses_local_sudo(&self, program: &ProgramEntry) -> bool {
+        std::iter::once(program).chain(self.config.prerequisites(program)).any(|entry| {
+            let steps = entry.steps.iter().chain(&entry.check);
+            let mut elevations = self
+                .config
+                .entry_elevation(entry)
+                .into_iter()
+                .chain(steps.filter_map(|step| self.config.step_elevation(entry, step)));
+            self.targets(entry).is_empty() && elevations.any(|elevation| elevation.backend == Backend::Sudo)
+        })
+    }
+
+    fn sudo_cached(&self) -> bool {
+        self.sudo_cached_at.is_some_and(|at| at.elapsed() < sudo::CACHE_WINDOW)
+    }
+
+    /// Starts following a log where the entry runs and opens the viewer.
+    fn open_logs(&mut self, title: String, source: &LogSource, host: Option<&SshHost>) {
+        let (command, args) = source.follow_command();
+        match LogTail::spawn(Self::build_command(host, command, &args, None, false)) {
+            Ok(tail) => {
+                self.log_view = Some(LogView {
+                    title,
+                    tail,
+                    filter: None,
+                    filter_input: None,
+                    filter_error: None,
+                    paused_at: None,
+                    scroll: 0,
+                    ended: false,
+                    return_to: self.mode.clone(),
+                    drawn_total: 0,
+                });
+                self.mode = Mode::Logs;
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Cannot follow logs of {}: {}", title, e));
+            }
+        }
+    }
+
+    /// Returns whether the log has changed since it was last drawn.
+    fn poll_logs(&mut self) -> io::Result<bool> {
+        let Some(view) = self.log_view.as_mut() else {
+            return Ok(false);
+        };
+        let ended = view.tail.try_wait()?.is_some();
+        view.ended |= ended;
+        let total = view.tail.lines().total();
+        let changed = ended || total != view.drawn_total;
+        view.drawn_total = total;
+        Ok(changed)
+    }
+
+    /// Lists the units of the active host in the background; the view
+    /// shows the ones it has until then.
+    fn refresh_units(&mut self) {
+        let host = self.active_host.as_deref().map(|h| self.config.resolve_host(h));
+        let cmd = Self::build_command(host.as_ref(), systemd::LIST_COMMAND, &systemd::list_args(), None, false);
+        self.start_listing(Listing::Units, cmd);
+    }
+
+    fn visible_units(&self) -> Vec<&Unit> {
+        self.units.iter().filter(|unit| !self.failed_units_only || unit.is_failed()).collect()
+    }
+
+    /// Adds the selected unit's status to the dashboard as a normal entry.
+    fn pin_selected_unit(&mut self) {
+        let Some(unit) = self.visible_units().get(self.unit_index).map(|u| (*u).clone()) else {
+            return;
+        };
+        let entry = systemd::pinned_entry(&unit, self.active_host.clone());
+        if self.config.programs.contains_key(&entry.name) {
+            self.status_message = Some(format!("Already pinned: {}", unit.name));
+            return;
+        }
+
+        self.config.add_program(entry);
+        self.status_message = Some(match self.config.save() {
+            Ok(()) => format!("Pinned: {}", unit.name),
+            Err(e) => format!("Error saving config: {}", e),
+        });
+    }
+
+    /// The entries of the host switcher: local first, then the inventory.
+    /// An active host picked from `~/.ssh/config` by name stays listed.
+    fn host_choices(&self) -> Vec<Option<String>> {
+        let mut hosts = vec![None];
+        hosts.extend(self.config.get_hosts().into_iter().map(|h| Some(h.name.clone())));
+        if !hosts.contains(&self.active_host) {
+            hosts.push(self.active_host.clone());
+        }
+        hosts
+    }
+
+    fn targets(&self, program: &ProgramEntry) -> Vec<SshHost> {
+        self.config.targets(program, self.active_host.as_deref())
+    }
+
+    fn is_multi_host(&self, program: &ProgramEntry) -> bool {
+        self.targets(program).len() > 1
+    }
+
+    fn build_command(
+        host: Option<&SshHost>,
+        command: &str,
+        args: &[String],
+        elevation: Option<&Elevation>,
+        interactive: bool,
+    ) -> Command {
+        if let Some(host) = host {
+            return ssh::command(host, &ssh::remote_command(command, args, elevation, interactive), interactive);
+        }
+
+        let mut cmd = if let Some(elevation) = elevation {
+            // Captured sudo runs rely on cached credentials; see `launch_entry`
+            let prefix = elevation.prefix(interactive);
+            let mut cmd = Command::new(&prefix[0]);
+            cmd.args(&prefix[1..]).arg(command);
+            cmd
+        } else {
+            Command::new(command)
+        };
+        if !args.is_empty() {
+            cmd.args(args);
+        }
+        cmd
+    }
+
+    /// A command of an entry, run inside the entry's container if it has
+    /// one and on the given host.
+    fn entry_command(
+        &self,
+        program: &ProgramEntry,
+        host: Option<&SshHost>,
+        command: &str,
+        args: &[String],
+        elevation: Option<Elevation>,
+        interactive: bool,
+    ) -> Command {
+        let (command, args) = Self::with_color(program, command, args, interactive);
+        let (command, args) = self.in_container(program, &command, &args, interactive);
+        Self::build_command(host, &command, &args, elevation.as_ref(), interactive)
+    }
+
+    /// A captured command of an entry that forces colour, run through `env`
+    /// so the variables survive sudo, ssh and container engines.
+    fn with_color(program: &ProgramEntry, command: &str, args: &[String], interactive: bool) -> (String, Vec<String>) {
+        if !program.color || interactive {
+            return (command.to_string(), args.to_vec());
+        }
+        let mut env_args: Vec<String> = COLOR_ENV.iter().map(|var| var.to_string()).collect();
+        env_args.push(command.to_string());
+        env_args.extend_from_slice(args);
+        ("env".to_string(), env_args)
+    }
+
+    /// The container engine invocation for a command when the entry runs
+    /// in a container; with sudo, the engine itself runs as root.
+    fn in_container(&self, program: &ProgramEntry, command: &str, args: &[String], interactive: bool) -> (String, Vec<String>) {
+        match program.container.as_deref() {
+            Some(name) => (
+                container::engine(self.config.container_engine.as_deref()),
+                container::exec_args(name, command, args, interactive),
+            ),
+            None => (command.to_string(), args.to_vec()),
+        }
+    }
+
+    fn program_command(&self, program: &ProgramEntry, interactive: bool) -> Command {
+        let host = self.targets(program).into_iter().next();
+        let elevation = self.config.entry_elevation(program);
+        self.entry_command(program, host.as_ref(), &program.command, &program.args, elevation, interactive)
+    }
+
+    /// Where an entry's commands run: on each of its hosts, or locally.
+    fn run_targets(&self, program: &ProgramEntry) -> Vec<Option<SshHost>> {
+        let targets = self.targets(program);
+        if targets.is_empty() {
+            vec![None]
+        } else {
+            targets.into_iter().map(Some).collect()
+        }
+    }
+
+    /// The shell command line an entry runs on each remote host; workflow
+    /// steps are chained according to the entry's failure policy, each
+    /// under a header like the local progress output and under `timeout`
+    /// when it has its own.
+    fn remote_script(&self, program: &ProgramEntry) -> String {
+        let remote = |command: &str, args: &[String], elevation: Option<Elevation>| {
+            let (command, args) = Self::with_color(program, command, args, false);
+            let (command, args) = self.in_container(program, &command, &args, false);
+            ssh::remote_command(&command, &args, elevation.as_ref(), false)
+        };
+        if !program.is_workflow() {
+            return remote(&program.command, &program.args, self.config.entry_elevation(program));
+        }
+
+        let separator = if program.on_failure == FailurePolicy::Continue { " ; " } else { " && " };
+        let count = program.steps.len();
+        program
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let header = ssh::quote(&format!("==> [{}/{}] {}", i + 1, count, step.label()));
+                let line = remote(&step.command, &step.args, self.config.step_elevation(program, step));
+                match step.timeout {
+                    // timeout(1) exits with 124 when it stopped the command
+                    Some(seconds) => format!(
+                        "echo {} && {{ timeout {} {}; rc=$?; [ $rc -ne 124 ] || echo {}; [ $rc -eq 0 ]; }}",
+                        header,
+                        seconds,
+                        line,
+                        ssh::quote(&format!("Timed out after {}s", seconds))
+                    ),
+                    None => format!("echo {} && {}", header, line),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    /// The captured commands to run for an entry: its workflow steps, or
+    /// the entry's own command as a single step, once per host. Steps of a
+    /// prerequisite always stop what depends on it when they fail.
+    fn entry_steps(&self, program: &ProgramEntry, as_prerequisite: bool) -> Vec<WorkflowStep> {
+        self.run_targets(program)
+            .into_iter()
+            .flat_map(|host| self.host_steps(program, host.as_ref(), as_prerequisite))
+            .collect()
+    }
+
+    fn host_steps(&self, program: &ProgramEntry, host: Option<&SshHost>, as_prerequisite: bool) -> Vec<WorkflowStep> {
+        let display_name = &program.display_name;
+        let timeout = self.config.timeout_for(program);
+        let multi_host = self.is_multi_host(program);
+        let on_host = |label: String| match host {
+            Some(host) if multi_host => format!("{} @ {}", label, host.alias),
+            _ => label,
+        };
+
+        if !program.is_workflow() {
+            let elevation = self.config.entry_elevation(program);
+            let command = self.entry_command(program, host, &program.command, &program.args, elevation, false);
+            return vec![WorkflowStep::new(program.name.clone(), on_host(display_name.clone()), command, timeout)];
+        }
+
+        let continue_on_failure = program.on_failure == FailurePolicy::Continue && !as_prerequisite;
+        program
+            .steps
+            .iter()
+            .map(|step| {
+                let label = if as_prerequisite { format!("{}: {}", display_name, step.label()) } else { step.label() };
+                let step = WorkflowStep::new(
+                    program.name.clone(),
+                    on_host(label),
+                    self.entry_command(
+                        program,
+                        host,
+                        &step.command,
+                        &step.args,
+                        self.config.step_elevation(program, step),
+                        false,
+                    ),
+                    step.timeout.map(Duration::from_secs).or(timeout),
+                );
+                if continue_on_failure {
+                    step.continue_on_failure()
+                } else {
+                    step
+                }
+            })
+            .collect()
+    }
+
+    /// Steps for every prerequisite of an entry, in dependency order, each
+    /// preceded by its health check if it has one.
+    fn prerequisite_steps(&self, program: &ProgramEntry) -> Vec<WorkflowStep> {
+        self.config
+            .prerequisites(program)
+            .into_iter()
+            .flat_map(|prerequisite| self.steps_as_prerequisite(prerequisite))
+            .collect()
+    }
+
+    /// The steps of one prerequisite on each of its hosts, each preceded by
+    /// its health check if it has one.
+    fn steps_as_prerequisite(&self, prerequisite: &ProgramEntry) -> Vec<WorkflowStep> {
+        let mut steps = Vec::new();
+        let multi_host = self.is_multi_host(prerequisite);
+        for host in self.run_targets(prerequisite) {
+            let host = host.as_ref();
+            let own_steps = self.host_steps(prerequisite, host, true);
+            if let Some(check) = &prerequisite.check {
+                let mut label = format!("{} check: {}", prerequisite.display_name, check.label());
+                if let Some(host) = host.filter(|_| multi_host) {
+                    label = format!("{} @ {}", label, host.alias);
+                }
+                steps.push(WorkflowStep::check(
+                    prerequisite.name.clone(),
+                    label,
+                    self.entry_command(
+                        prerequisite,
+                        host,
+                        &check.command,
+                        &check.args,
+                        self.config.step_elevation(prerequisite, check),
+                        false,
+                    ),
+                    check.timeout.map(Duration::from_secs).or(self.config.timeout_for(prerequisite)),
+                    own_steps.len(),
+                ));
+            }
+            steps.extend(own_steps);
+        }
+        steps
+    }
+
+    fn launch_selected_program(&mut self) -> io::Result<()> {
+        let Some(program) = self.config.get_programs().get(self.selected_index).map(|p| (*p).clone()) else {
+            return Ok(());
+        };
+
+        // Counts only decide the order of the list, so failing to save
+        // them is not worth interrupting the launch for
+        let _ = self.config.record_launch(&program.name);
+        if self.config.sort == SortOrder::Usage {
+            self.select_program(&program.name);
+        }
+
+        if program.picks_container() {
+            self.open_container_picker(program);
+            Ok(())
+        } else {
+            self.launch_entry(program)
+        }
+    }
+
+    fn launch_entry(&mut self, program: ProgramEntry) -> io::Result<()> {
+        // Entries on several hosts run as a batch once any prerequisites are done
+        let captured = (program.show_output || program.is_workflow()) && !self.is_multi_host(&program);
+        if captured || !program.depends_on.is_empty() {
+            // Captured runs cannot show sudo's prompt, so ask for it first
+            if self.uses_local_sudo(&program) && !self.authenticate_sudo(Some(program.clone())) {
+                return Ok(());
+            }
+            self.start_workflow(&program, captured);
+            Ok(())
+        } else {
+            self.launch_program(&program)
+        }
+    }
+
+    /// Lists the running containers where the entry runs and lets the user
+    /// pick the one to launch it in.
+    fn open_container_picker(&mut self, program: ProgramEntry) {
+        let targets = self.targets(&program);
+        if targets.len() > 1 {
+            self.status_message = Some(format!("Cannot pick a container for {}: it runs on several hosts", program.display_name));
+            return;
+        }
+
+        let engine = container::engine(self.config.container_engine.as_deref());
+        let elevation = self.config.entry_elevation(&program);
+        let cmd = Self::build_command(targets.first(), &engine, &container::list_args(), elevation.as_ref(), false);
+        self.status_message = Some(format!("Listing containers for {}...", program.display_name));
+        self.start_listing(Listing::Containers(Box::new(program)), cmd);
+    }
+
+    /// Runs the command that lists something in the background;
+    /// `poll_listing` acts on the list once it is there.
+    fn start_listing(&mut self, listing: Listing, cmd: Command) {
+        match Job::spawn(cmd, Some(LIST_TIMEOUT)) {
+            Ok(job) => self.listing = Some((listing, job)),
+            Err(e) => self.status_message = Some(format!("Cannot list {}: {}", listing.noun(), e)),
+        }
+    }
+
+    /// Returns whether a listing finished. Containers open the picker,
+    /// unless another screen has been opened in the meantime; units fill
+    /// the unit view.
+    fn poll_listing(&mut self) -> io::Result<bool> {
+        let result = match self.listing.as_mut() {
+            Some((_, job)) => match job.poll()? {
+                Some(result) => result,
+                None => return Ok(false),
+            },
+            None => return Ok(false),
+        };
+        let (listing, _) = self.listing.take().unwrap();
+
+        if !result.success() {
+            let error = match result.end {
+                JobEnd::TimedOut => format!("timed out after {}s", LIST_TIMEOUT.as_secs()),
+                _ => result.stderr.lines().next().unwrap_or_default().to_string(),
+            };
+            self.status_message = Some(format!("Cannot list {}: {}", listing.noun(), error));
+            return Ok(true);
+        }
+
+        match listing {
+            Listing::Containers(program) => {
+                let containers = container::parse_list(&result.stdout);
+                if containers.is_empty() {
+                    self.status_message = Some("No running containers".to_string());
+                } else {
+                    self.status_message = None;
+                    if self.mode == Mode::Normal {
+                        self.container_pick = Some((*program, containers));
+                        self.container_index = 0;
+                        self.mode = Mode::ContainerPicker;
+                    }
+                }
+            }
+            Listing::Units => match systemd::parse_units(&result.stdout) {
+                Ok(units) => {
+                    self.units = units;
+                    self.unit_index = self.unit_index.min(self.visible_units().len().saturating_sub(1));
+                }
+                Err(e) => self.status_message = Some(format!("Cannot list units: {}", e)),
+            },
+        }
+        Ok(true)
+    }
+
+    /// Runs an entry's prerequisites and, for captured entries, the entry
+    /// itself in the background. Other entries are launched once their
+    /// prerequisites have succeeded.
+    fn start_workflow(&mut self, program: &ProgramEntry, captured: bool) {
+        // Capture output in the background so the dashboard stays responsive
+        if let Some((running, _, _)) = &self.job {
+            self.status_message = Some(format!("Already running: {}", running));
+            return;
+        }
+
+        let mut steps = self.prerequisite_steps(program);
+        if captured {
+            steps.extend(self.entry_steps(program, false));
+        }
+        let show_progress = steps.len() > 1;
+
+        match Workflow::start(steps) {
+            Ok(workflow) => {
+                self.status_message = None;
+                self.job = Some((program.display_name.clone(), vec![program.name.clone()], workflow));
+                self.pending_launch = (!captured).then(|| program.name.clone());
+                if show_progress {
+                    self.mode = Mode::Progress;
+                }
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Error launching {}: {}", program.display_name, e));
+            }
+        }
+    }
+
+    /// Runs an entry on all of its hosts in parallel and opens the per-host
+    /// result table.
+    fn start_batch(&mut self, program: &ProgramEntry) {
+        if let Some((running, batch)) = &self.batch {
+            if !batch.is_finished() {
+                self.status_message = Some(format!("Already running: {}", running));
+                return;
+            }
+        }
+
+        let known_hosts = ssh::load_hosts();
+        let remote = self.remote_script(program);
+        let timeout = self.config.timeout_for(program);
+        let items = self
+            .targets(program)
+            .into_iter()
+            .map(|host| {
+                // Inventory hosts carry their address; others may be ssh aliases
+                let address = match known_hosts.iter().find(|known| known.alias == host.alias) {
+                    Some(known) if host.hostname.is_none() => known.address(),
+                    _ => host.address(),
+                };
+                let command = ssh::command(&host, &remote, false);
+                BatchItem::new(host.alias, address, command, timeout)
+            })
+            .collect();
+
+        let limit = program.parallel.unwrap_or(DEFAULT_PARALLEL_HOSTS);
+        self.status_message = None;
+        self.batch = Some((program.display_name.clone(), Batch::start(items, limit)));
+        self.batch_kind = BatchKind::Hosts;
+        self.batch_index = 0;
+        self.mode = Mode::Batch;
+    }
+
+    /// Launches an entry without capturing its output, either in the
+    /// embedded pane or in place of the dashboard. Entries on several hosts
+    /// always run captured, as a batch.
+    fn launch_program(&mut self, program: &ProgramEntry) -> io::Result<()> {
+        if self.is_multi_host(program) {
+            self.start_batch(program);
+        } else if program.embedded {
+            if let Some((running, _)) = &self.pane {
+                self.status_message = Some(format!("Already running in pane: {}", running));
+                return Ok(());
+            }
+
+            // Without room for the pane the program gets the whole screen's
+            // size until the terminal grows
+            let (width, height) = terminal::size()?;
+            let (_, _, cols, rows) = Self::pane_area((width, height)).unwrap_or((0, 0, width, height));
+            match PtySession::spawn(self.program_command(program, true), rows, cols) {
+                Ok(session) => {
+                    self.status_message = None;
+                    self.pane = Some((program.display_name.clone(), session));
+                    self.pane_hidden = false;
+                    self.focus = Focus::Pane;
+                }
+                Err(e) => {
+                    self.status_message = Some(format!("Error launching {}: {}", program.display_name, e));
+                }
+            }
+        } else {
+            // Regular execution without capturing output
+            // Save current terminal state
+            execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen, Show)?;
+            terminal::disable_raw_mode()?;
+
+            let result = self.program_command(program, true).status();
+
+            // Restore terminal state
+            terminal::enable_raw_mode()?;
+            execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+            self.capture_mouse()?;
+            self.renderer.invalidate();
+
+            match result {
+                Ok(status) => {
+                    if status.success() {
+                        self.status_message = Some(format!("Executed: {}", program.display_name));
+                    } else {
+                        self.status_message = Some(format!("Failed to execute: {}", program.display_name));
+                    }
+                }
+                Err(e) => {
+                    self.status_message = Some(format!("Error launching {}: {}", program.display_name, e));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the job has moved on to another step or finished.
+    fn poll_job(&mut self) -> io::Result<bool> {
+        let (result, step_changed) = match self.job.as_mut() {
+            Some((_, _, job)) => {
+                let step = job.current_step().map(|(i, _)| i);
+                let result = job.poll()?;
+                (result, job.current_step().map(|(i, _)| i) != step)
+            }
+            None => return Ok(false),
+        };
+
+        let Some(result) = result else {
+            return Ok(step_changed);
+        };
+        let (display_name, names, workflow) = self.job.take().unwrap();
+        let pending_launch = self.pending_launch.take();
+
+        if result.outcome == Outcome::Succeeded {
+            if let Some(program) = pending_launch.and_then(|name| self.config.programs.get(&name).cloned()) {
+                // Prerequisites are ready; launch the entry itself unless the
+                // user has moved on to another screen
+                if matches!(self.mode, Mode::Normal | Mode::Progress) {
+                    self.mode = Mode::Normal;
+                    self.launch_program(&program)?;
+                    return Ok(true);
+                }
+                self.status_message = Some(format!("Prerequisites ready: {}", display_name));
+                return Ok(true);
+            }
+        }
+
+        let failed_prerequisite = workflow
+            .first_failure()
+            .filter(|step| !names.contains(&step.owner))
+            .map(|step| self.config.programs.get(&step.owner).map_or(&step.owner, |p| &p.display_name));
+        self.status_message = Some(match (result.outcome, failed_prerequisite) {
+            (Outcome::Killed, _) => format!("Killed: {}", display_name),
+            (_, Some(prerequisite)) => format!("Prerequisite failed: {} (needed by {})", prerequisite, display_name),
+            (Outcome::Succeeded, None) => format!("Executed: {}", display_name),
+            (Outcome::Failed, None) => format!("Executed with errors: {}", display_name),
+            (Outcome::TimedOut, None) => format!(
+                "Timed out after {}s: {}",
+                result.elapsed.as_secs(),
+                display_name
+            ),
+        });
+
+        // A cancelled job was stopped on purpose; its partial output is not shown
+        if result.outcome != Outcome::Killed {
+            self.output_view = Some(OutputView::new(display_name, &result.output));
+            if self.mode == Mode::Systemd {
+                self.output_return = Mode::Systemd;
+            }
+            if self.mode != Mode::AddProgram {
+                self.mode = Mode::ShowOutput;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns whether any item of the batch has finished.
+    fn poll_batch(&mut self) -> io::Result<bool> {
+        let (outcome, completed) = match self.batch.as_mut() {
+            Some((_, batch)) if !batch.is_finished() => {
+                let completed = batch.completed();
+                (batch.poll()?, batch.completed() != completed)
+            }
+            _ => return Ok(false),
+        };
+
+        if let (Some(outcome), Some((display_name, batch))) = (outcome, &self.batch) {
+            let total = batch.items().len();
+            let succeeded = batch.items().iter().filter(|item| item.state == StepState::Succeeded).count();
+            let summary = format!("{} of {} {} succeeded", succeeded, total, self.batch_kind.noun());
+            self.status_message = Some(match outcome {
+                Outcome::Killed => format!("Killed: {} ({})", display_name, summary),
+                Outcome::TimedOut => format!("Timed out: {} ({})", display_name, summary),
+                Outcome::Failed => format!("Executed with errors: {} ({})", display_name, summary),
+                Outcome::Succeeded => format!("Executed: {} ({})", display_name, summary),
+            });
+        }
+        Ok(completed || outcome.is_some())
+    }
+
+    fn has_background_work(&self) -> bool {
+        self.job.is_some()
+            || self.pane.is_some()
+            || self.batch.as_ref().is_some_and(|(_, batch)| !batch.is_finished())
+            || self.log_view.is_some()
+            || self.quote_command.is_some()
+            || self.listing.is_some()
+    }
+
+    /// Returns whether the embedded program has drawn anything or exited.
+    fn poll_pane(&mut self) -> io::Result<bool> {
+        let (status, updated) = match self.pane.as_mut() {
+            Some((_, pane)) => (pane.try_wait()?, pane.take_updated()),
+            None => return Ok(false),
+        };
+
+        if let Some(status) = status {
+            let (display_name, _) = self.pane.take().unwrap();
+            self.focus = Focus::List;
+            self.pane_hidden = false;
+            self.status_message = Some(if status.success() {
+                format!("Exited: {}", display_name)
+            } else {
+                format!("Exited with errors: {}", display_name)
+            });
+        }
+        Ok(updated || status.is_some())
+    }
+
+    /// Detaches the embedded program into the background or brings it back.
+    fn toggle_pane(&mut self) {
+        match &self.pane {
+            Some((display_name, _)) => {
+                self.pane_hidden = !self.pane_hidden;
+                self.focus = if self.pane_hidden { Focus::List } else { Focus::Pane };
+                if self.pane_hidden {
+                    self.status_message = Some(format!("Running in background: {}", display_name));
+                }
+            }
+            None => {
+                self.status_message = Some("No embedded program running".to_string());
+            }
+        }
+    }
+
+    /// Whether the embedded program is on screen: running, not in the
+    /// background, and with room for it on the terminal.
+    fn pane_visible(&self) -> bool {
+        self.pane.is_some() && !self.pane_hidden && terminal::size().is_ok_and(|size| Self::pane_area(size).is_some())
+    }
+
+    fn pane_focused(&self) -> bool {
+        self.pane_visible() && self.focus == Focus::Pane
+    }
+
+    /// Rows available to the entry list, between the header and the status
+    /// line or the embedded pane.
+    fn list_rows(&self, (width, height): (u16, u16)) -> usize {
+        let bottom = match Self::pane_area((width, height)) {
+            // Stop above the pane's title line
+            Some((_, pane_y, _, _)) if self.pane_visible() => pane_y.saturating_sub(1),
+            _ => height.saturating_sub(3),
+        };
+        bottom.saturating_sub(LIST_TOP) as usize
+    }
+
+    /// Indices of the first and last entries on screen, for H/M/L.
+    fn shown_range(&self, size: (u16, u16)) -> (usize, usize) {
+        let count = self.config.get_programs().len();
+        let last = (self.list_offset + self.list_rows(size).max(1)).min(count);
+        (self.list_offset, last.saturating_sub(1))
+    }
+
+    /// Area of the embedded program as (x, y, columns, rows): the lower two
+    /// thirds of the screen between the top bar and the status line, below a
+    /// one-line title. None when the screen is too short to fit it.
+    fn pane_area((width, height): (u16, u16)) -> Option<(u16, u16, u16, u16)> {
+        let top = (height / 3).max(6).min(height);
+        let rows = height.saturating_sub(4 + top);
+        (rows > 0).then_some((0, top + 1, width.max(1), rows))
+    }
+
+    fn cancel_job(&mut self) {
+        match self.job.as_mut() {
+            Some((display_name, _, job)) => {
+                job.cancel();
+                self.status_message = Some(format!("Cancelling: {}", display_name));
+            }
+            None => self.cancel_batch(),
+        }
+    }
+
+    fn cancel_batch(&mut self) {
+        match self.batch.as_mut() {
+            Some((display_name, batch)) if !batch.is_finished() => {
+                batch.cancel();
+                self.status_message = Some(format!("Cancelling: {}", display_name));
+            }
+            _ => {
+                self.status_message = Some("No running program to cancel".to_string());
+            }
+        }
+    }
+
+    /// Leaves a mode back to the dashboard, showing output that finished
+    /// in the meantime.
+    fn return_to_normal(&mut self) {
+        self.mode = if self.output_view.is_some() { Mode::ShowOutput } else { Mode::Normal };
+    }
+
+    fn delete_selected_program(&mut self) -> io::Result<()> {
+        let programs = self.config.get_programs();
+        if let Some(program) = programs.get(self.selected_index) {
+            let name = program.name.clone();
+            let display_name = program.display_name.clone();
+
+            let dependents = self.config.dependents(&name);
+            if !dependents.is_empty() {
+                let names: Vec<_> = dependents.iter().map(|p| p.display_name.as_str()).collect();
+                self.status_message = Some(format!("Cannot delete {}: required by {}", display_name, names.join(", ")));
+                return Ok(());
+            }
+
+            if self.config.remove_program(&name) {
+                if let Err(e) = self.config.save() {
+                    self.status_message = Some(format!("Error saving config: {}", e));
+                } else {
+                    self.status_message = Some(format!("Deleted: {}", display_name));
+                    // Adjust selected index if necessary
+                    let new_len = self.config.get_programs().len();
+                    if new_len > 0 && self.selected_index >= new_len {
+                        self.selected_index = new_len - 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn save_new_program(&mut self) -> io::Result<()> {
+        let args: Vec<String> = if self.add_form.args.is_empty() {
+            vec![]
+        } else {
+            self.add_form.args.split_whitespace().map(|s| s.to_string()).collect()
+        };
+
+        let entry = ProgramEntry {
+            name: self.add_form.name.clone(),
+            display_name: self.add_form.display_name.clone(),
+            command: self.add_form.command.clone(),
+            args,
+            description: if self.add_form.description.is_empty() {
+                None
+            } else {
+                Some(self.add_form.description.clone())
+            },
+            run_with_sudo: self.add_form.run_with_sudo,
+            show_output: self.add_form.show_output,
+            timeout: None,
+            embedded: false,
+            steps: vec![],
+            on_failure: FailurePolicy::Stop,
+            depends_on: vec![],
+            check: None,
+            host: None,
+            hosts: vec![],
+            parallel: None,
+            container: None,
+            logs: None,
+            run_as: None,
+            privilege: None,
+            hotkey: None,
+            group: None,
+            color: false,
+        };
+
+        self.config.add_program(entry);
+        
+        if let Err(e) = self.config.save() {
+            self.status_message = Some(format!("Error saving config: {}", e));
+        } else {
+            self.status_message = Some(format!("Added: {}", self.add_form.display_name));
+        }
+
+        self.add_form.reset();
+        Ok(())
+    }
+
+    fn reload_config(&mut self) -> io::Result<()> {
+        match Config::load() {
+            Ok(config) => {
+                // `load` has already checked the keys and theme
+                if let Ok(keymap) = config.keymap() {
+                    self.keymap = keymap;
+                }
+                if let Ok(theme) = config.theme() {
+                    self.theme = theme;
+                }
+                if config.metrics.is_none() {
+                    self.monitor = None;
+                } else if self.monitor.is_none() {
+                    self.monitor = Some(Monitor::new());
+                }
+                self.config = config;
+                self.capture_mouse()?;
+                self.selected_index = 0;
+                self.start_quote_command();
+                self.status_message = Some("Configuration reloaded".to_string());
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Error reloading config: {}", e));
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the quote command, if the quote is one, without waiting for it.
+    fn start_quote_command(&mut self) {
+        self.command_quote = None;
+        self.quote_command = None;
+        let Some(Quote::Command { command, args }) = self.config.quote.clone() else {
+            return;
+        };
+        let mut cmd = Command::new(&command);
+        cmd.args(&args);
+        match Job::spawn(cmd, Some(QUOTE_TIMEOUT)) {
+            Ok(job) => self.quote_command = Some(job),
+            Err(e) => self.command_quote = Some(format!("{}: {}", command, e)),
+        }
+    }
+
+    /// Returns whether the quote command has finished.
+    fn poll_quote(&mut self) -> io::Result<bool> {
+        let Some(job) = self.quote_command.as_mut() else { return Ok(false) };
+        let Some(result) = job.poll()? else { return Ok(false) };
+        self.command_quote = Some(match result.end {
+            JobEnd::TimedOut => format!("Quote command timed out after {}s", QUOTE_TIMEOUT.as_secs()),
+            _ => result.stdout.split_whitespace().collect::<Vec<_>>().join(" "),
+        });
+        self.quote_command = None;
+        Ok(true)
+    }
+
+    fn poll_metrics(&mut self) {
+        if let Some(monitor) = self.monitor.as_mut().filter(|monitor| monitor.is_due()) {
+            monitor.refresh();
+        }
+    }
+
+    /// Columns the metrics sidebar takes at this terminal width; 0 when it
+    /// is not shown.
+    fn sidebar_width(&self, width: u16) -> u16 {
+        match (self.config.metrics, &self.monitor) {
+            (Some(MetricsLayout::Sidebar), Some(_)) if width >= SIDEBAR_MIN_WIDTH => SIDEBAR_WIDTH,
+            _ => 0,
+        }
+    }
+
+    /// The value of a header, footer or quote placeholder; `keys` is the
+    /// key bar the footer would show.
+    fn placeholder(&self, name: &str, keys: &str) -> Option<String> {
+        match name {
+            "hostname" => sysinfo::hostname(),
+            "user" => sysinfo::user(),
+            "time" => sysinfo::local_time().map(|tm| format!("{:02}:{:02}", tm.tm_hour, tm.tm_min)),
+            "date" => sysinfo::local_time()
+                .map(|tm| format!("{}-{:02}-{:02}", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday)),
+            "load" => sysinfo::load_average().map(|(one, five, fifteen)| format!("{:.2} {:.2} {:.2}", one, five, fifteen)),
+            "uptime" => sysinfo::uptime().map(sysinfo::format_uptime),
+            "host" => Some(self.active_host.clone().unwrap_or_else(|| "local".to_string())),
+            "jobs" => {
+                let batch = self.batch.as_ref().is_some_and(|(_, batch)| !batch.is_finished());
+                let jobs = [self.job.is_some(), batch, self.pane.is_some()];
+                Some(jobs.iter().filter(|running| **running).count().to_string())
+            }
+            "keys" => Some(keys.to_string()),
+            _ => None,
+        }
+    }
+
+    /// The quote line as configured, with its placeholders filled in.
+    fn quote(&self) -> String {
+        let text = match self.config.quote.clone().unwrap_or_default() {
+            Quote::Text(text) => text,
+            Quote::List(lines) if lines.is_empty() => String::new(),
+            Quote::List(lines) => {
+                let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                let index = (elapsed.as_secs() / QUOTE_ROTATION.as_secs()) as usize % lines.len();
+                lines[index].clone()
+            }
+            Quote::Command { .. } => return self.command_quote.clone().unwrap_or_default(),
+        };
+        template::render(&text, |name| self.placeholder(name, ""))
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        
+        // Keep the embedded program's terminal the size of its pane
+        if let (Some((_, pane)), Some((_, _, cols, rows))) = (self.pane.as_mut(), Self::pane_area((width, height))) {
+            pane.resize(rows, cols)?;
+        }
+
+        // Scroll the list just enough to keep the selection in view
+        let rows = self.list_rows((width, height)).max(1);
+        if self.selected_index < self.list_offset {
+            self.list_offset = self.selected_index;
+        } else if self.selected_index >= self.list_offset + rows {
+            self.list_offset = self.selected_index + 1 - rows;
+        }
+
+        let mut frame = Renderer::frame((width, height));
+        let out = &mut frame;
+        match self.mode {
+            Mode::Normal | Mode::Command => self.draw_main_screen(out, width, height)?,
+            Mode::AddProgram => self.draw_add_program_screen(out, width, height)?,
+            Mode::Help => self.draw_help_screen(out, width, height)?,
+            Mode::ShowOutput => self.draw_output_screen(out, width, height)?,
+            Mode::Progress => self.draw_progress_screen(out, width, height)?,
+            Mode::Batch => self.draw_batch_screen(out, width, height)?,
+            Mode::HostSwitcher => self.draw_host_switcher_screen(out, width, height)?,
+            Mode::ContainerPicker => self.draw_container_picker_screen(out, width, height)?,
+            Mode::Systemd => self.draw_systemd_screen(out, width, height)?,
+            Mode::Logs => self.draw_logs_screen(out, width, height)?,
+            Mode::SudoPrompt => self.draw_sudo_prompt_screen(out, width, height)?,
+            Mode::Selection => self.draw_selection_screen(out, width, height)?,
+        }
+
+        self.renderer.render(frame)
+    }
+
+    fn entry_text(&self, program: &ProgramEntry) -> String {
+        let sudo_indicator = self
+            .config
+            .entry_elevation(program)
+            .map_or_else(String::new, |elevation| format!(" [{}]", elevation.label()));
+        let output_indicator = if program.show_output { " [OUT]" } else { "" };
+        let flow_indicator = if program.is_workflow() { " [FLOW]" } else { "" };
+        // Entries following the active host show it in the top bar instead
+        let host_indicator = match self.config.targets(program, None).as_slice() {
+            [] => String::new(),
+            [host] => format!(" [@{}]", host.alias),
+            hosts => format!(" [@{} hosts]", hosts.len()),
+        };
+        let container_indicator = program.container.as_ref().map_or_else(String::new, |c| format!(" [ctr:{}]", c));
+        let group_indicator = program.group.as_ref().map_or_else(String::new, |g| format!(" [group:{}]", g));
+        let indicators = format!(
+            "{}{}{}{}{}{}",
+            sudo_indicator, output_indicator, flow_indicator, host_indicator, container_indicator, group_indicator
+        );
+        if let Some(ref desc) = program.description {
+            format!("[ {}{} - {} ]", program.display_name, indicators, desc)
+        } else {
+            format!("[ {}{} ]", program.display_name, indicators)
+        }
+    }
+
+    fn draw_main_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        // Draw top bar
+        let mut title = match &self.config.header {
+            Some(header) => template::render(header, |name| self.placeholder(name, "")),
+            None => " RDash - Server Dashboard".to_string(),
+        };
+        if let Some(host) = &self.active_host {
+            title = format!("{} [@{} ({})]", title, host, self.config.resolve_host(host).address());
+        }
+        if self.sudo_cached() {
+            title.push_str(" [sudo cached]");
+        }
+        let marked: HashSet<String> = self.selected_programs().into_iter().map(|p| p.name).collect();
+        if self.visual_anchor.is_some() {
+            title.push_str(" [VISUAL]");
+        }
+        if !marked.is_empty() {
+            title = format!("{} [{} selected]", title, marked.len());
+        }
+        queue!(
+            out,
+            MoveTo(0, 0),
+            self.theme.header,
+            Print(text::fit(&title, width as usize)),
+            ResetColor
+        )?;
+
+        // Draw programs list
+        let programs = self.config.get_programs();
+        let start_y = 2;
+
+        // Draw the quote line
+        let quote = text::truncate(&self.quote(), width.saturating_sub(4) as usize);
+        let quote_x = text::centered(text::width(&quote), width, 2);
+
+        queue!(
+            out,
+            MoveTo(quote_x, start_y),
+            self.theme.dim,
+            Print(&quote),
+            ResetColor
+        )?;
+
+        let programs_start_y = LIST_TOP;
+
+        // Metrics go right of the list when there is room, else on one line
+        // under the top bar
+        let sidebar_width = self.sidebar_width(width);
+        let list_width = width - sidebar_width;
+        if let Some(monitor) = &self.monitor {
+            if sidebar_width > 0 {
+                self.draw_sidebar(out, monitor.metrics(), list_width, width, height)?;
+            } else {
+                let line = Self::metrics_line(monitor.metrics());
+                queue!(
+                    out,
+                    MoveTo(1, 1),
+                    self.theme.dim,
+                    Print(text::truncate(&line, width.saturating_sub(2) as usize)),
+                    ResetColor
+                )?;
+            }
+        }
+
+        if programs.is_empty() {
+            let empty_message = match self.keymap.chords(KeyMode::Normal, Action::Add).first() {
+                Some(chord) => format!("No programs configured. Press '{}' to add a program.", chord),
+                None => "No programs configured. Use :add to add a program.".to_string(),
+            };
+            let start_x = text::centered(text::width(&empty_message), list_width, 2);
+
+            queue!(
+                out,
+                MoveTo(start_x, programs_start_y + 2),
+                Print(empty_message)
+            )?;
+        } else {
+            // Hotkey badges go in a column of their own left of the entries
+            let badges: Vec<String> = programs
+                .iter()
+                .map(|program| self.config.hotkey(program).map_or_else(String::new, |h| self.keymap.hotkey_label(h)))
+                .collect();
+            let badge_width = badges.iter().map(|badge| text::width(badge)).max().unwrap_or(0);
+            let badge_column = if badge_width > 0 { badge_width + 1 } else { 0 };
+
+            // Calculate the maximum width needed for centering
+            let max_program_width =
+                badge_column + programs.iter().map(|program| text::width(&self.entry_text(program))).max().unwrap_or(0);
+            let start_x = text::centered(max_program_width, list_width, 2);
+
+            let rows = self.list_rows((width, height));
+            for (i, program) in programs.iter().enumerate().skip(self.list_offset).take(rows) {
+                let y = programs_start_y + (i - self.list_offset) as u16;
+                let is_selected = i == self.selected_index;
+
+                // Entries too long for the space left of the sidebar are cut short
+                let text_x = start_x + badge_column as u16;
+                let display_text = text::truncate(&self.entry_text(program), list_width.saturating_sub(text_x + 1) as usize);
+
+                if badge_column > 0 {
+                    queue!(
+                        out,
+                        MoveTo(start_x, y),
+                        self.theme.badge,
+                        Print(format!("{}{}", " ".repeat(badge_width - text::width(&badges[i])), badges[i])),
+                        ResetColor
+                    )?;
+                }
+
+                if is_selected {
+                    queue!(
+                        out,
+                        MoveTo(text_x, y),
+                        self.theme.selected,
+                        Print(&display_text),
+                        ResetColor
+                    )?;
+                } else if marked.contains(&program.name) {
+                    queue!(
+                        out,
+                        MoveTo(text_x, y),
+                        self.theme.marked,
+                        Print(&display_text),
+                        ResetColor
+                    )?;
+                } else {
+                    queue!(
+                        out,
+                        MoveTo(text_x, y),
+                        Print(&display_text)
+                    )?;
+                }
+            }
+        }
+
+        if self.pane_visible() {
+            self.draw_pane(out, width, height)?;
+        }
+
+        // Draw running job or status message if any
+        if let Some((display_name, _, job)) = &self.job {
+            let state = if job.is_cancelling() { "Cancelling" } else { "Running" };
+            let step = match job.current_step() {
+                Some((i, _)) if job.steps().len() > 1 => format!("step {}/{}, ", i + 1, job.steps().len()),
+                _ => String::new(),
+            };
+            queue!(
+                out,
+                MoveTo(2, height - 3),
+                self.theme.warning,
+                Print(format!(
+                    "{}: {} ({}{}s){}",
+                    state,
+                    display_name,
+                    step,
+                    job.elapsed().as_secs(),
+                    self.running_hint("progress")
+                )),
+                ResetColor
+            )?;
+        } else if let Some((display_name, batch)) = self.batch.as_ref().filter(|(_, b)| !b.is_finished()) {
+            let state = if batch.is_cancelling() { "Cancelling" } else { "Running" };
+            queue!(
+                out,
+                MoveTo(2, height - 3),
+                self.theme.warning,
+                Print(format!(
+                    "{}: {} ({}/{} {} done, {}s){}",
+                    state,
+                    display_name,
+                    batch.completed(),
+                    batch.items().len(),
+                    self.batch_kind.noun(),
+                    batch.elapsed().as_secs(),
+                    self.running_hint("results")
+                )),
+                ResetColor
+            )?;
+        } else if let Some(ref message) = self.status_message {
+            queue!(
+                out,
+                MoveTo(2, height - 3),
+                self.theme.success,
+                Print(message),
+                ResetColor
+            )?;
+        }
+
+        if self.mode == Mode::Command {
+            return self.draw_command_line(out, width, height);
+        }
+
+        // Draw bottom bar
+        let help_text = if self.pane_focused() {
+            format!(
+                "{}:back to dashboard | all other keys go to the embedded program",
+                self.keymap.keys(KeyMode::Normal, Action::FocusPane)
+            )
+        } else {
+            let keys = self.main_key_bar();
+            match &self.config.footer {
+                Some(footer) => template::render(footer, |name| self.placeholder(name, &keys)),
+                None => keys,
+            }
+        };
+        queue!(
+            out,
+            MoveTo(0, height - 1),
+            self.theme.footer,
+            Print(text::fit(&help_text, width as usize)),
+            ResetColor
+        )?;
+
+        // Keys typed so far of a count or multi-key motion, as Vim's showcmd
+        let pending = if self.leader_pending { self.keymap.leader().to_string() } else { self.keys.pending() };
+        if !pending.is_empty() {
+            queue!(
+                out,
+                MoveTo(width.saturating_sub(text::width(&pending) as u16 + 2), height - 1),
+                self.theme.pending,
+                Print(&pending),
+                ResetColor
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The metrics column from `x` to the right edge, as tall as the list.
+    fn draw_sidebar(&self, out: &mut Screen, metrics: &Metrics, x: u16, width: u16, height: u16) -> io::Result<()> {
+        let usage_style = |percent: f64| {
+            if percent >= 90.0 {
+                Some(self.theme.error)
+            } else if percent >= 75.0 {
+                Some(self.theme.warning)
+            } else {
+                None
+            }
+        };
+        let usage_text = |usage: metrics::Usage| {
+            format!(
+                "{:.0}% {}/{}",
+                usage.percent(),
+                metrics::format_bytes(usage.used as f64),
+                metrics::format_bytes(usage.total as f64)
+            )
+        };
+
+        let mut lines: Vec<(String, Option<Style>)> = vec![("SYSTEM".to_string(), Some(self.theme.dim))];
+        match metrics.cpu {
+            Some(cpu) => lines.push((format!("CPU   {:.0}%", cpu), usage_style(cpu))),
+            None => lines.push(("CPU   --".to_string(), None)),
+        }
+        if let Some((one, five, fifteen)) = metrics.load {
+            lines.push((format!("Load  {:.2} {:.2} {:.2}", one, five, fifteen), None));
+        }
+        if let Some(memory) = metrics.memory {
+            lines.push((format!("Mem   {}", usage_text(memory)), usage_style(memory.percent())));
+        }
+        if let Some(swap) = metrics.swap.filter(|swap| swap.total > 0) {
+            lines.push((format!("Swap  {}", usage_text(swap)), usage_style(swap.percent())));
+        }
+        if let Some(uptime) = metrics.uptime {
+            lines.push((format!("Up    {}", sysinfo::format_uptime(uptime)), None));
+        }
+        if let Some((received, sent)) = metrics.network {
+            let rate = |bytes| format!("{}/s", metrics::format_bytes(bytes));
+            lines.push((format!("Net   ↓{} ↑{}", rate(received), rate(sent)), None));
+        }
+        if !metrics.disks.is_empty() {
+            lines.push((String::new(), None));
+            lines.push(("DISKS".to_string(), Some(self.theme.dim)));
+        }
+        for (mount, usage) in &metrics.disks {
+            let mount = metrics::short_mount(mount, 8);
+            lines.push((format!("{} {}", text::fit(&mount, 8), usage_text(*usage)), usage_style(usage.percent())));
+        }
+
+        let rows = self.list_rows((width, height)) as u16;
+        let text_width = (width - x).saturating_sub(3) as usize;
+        for row in 0..rows {
+            queue!(out, MoveTo(x, LIST_TOP + row), self.theme.dim, Print("│"), ResetColor)?;
+        }
+        for (row, (line, style)) in lines.iter().take(rows as usize).enumerate() {
+            let line = text::truncate(line, text_width);
+            queue!(out, MoveTo(x + 2, LIST_TOP + row as u16))?;
+            match style {
+                Some(style) => queue!(out, *style, Print(line), ResetColor)?,
+                None => queue!(out, Print(line))?,
+            }
+        }
+        Ok(())
+    }
+
+    /// All the metrics on one line, for narrow terminals and the header
+    /// layout.
+    fn metrics_line(metrics: &Metrics) -> String {
+        let percent = |usage: metrics::Usage| format!("{:.0}%", usage.percent());
+        let mut parts = vec![format!("CPU {}", metrics.cpu.map_or_else(|| "--".to_string(), |cpu| format!("{:.0}%", cpu)))];
+        if let Some((one, five, fifteen)) = metrics.load {
+            parts.push(format!("Load {:.2} {:.2} {:.2}", one, five, fifteen));
+        }
+        if let Some(memory) = metrics.memory {
+            parts.push(format!("Mem {}", percent(memory)));
+        }
+        if let Some(swap) = metrics.swap.filter(|swap| swap.total > 0) {
+            parts.push(format!("Swap {}", percent(swap)));
+        }
+        if let Some(uptime) = metrics.uptime {
+            parts.push(format!("Up {}", sysinfo::format_uptime(uptime)));
+        }
+        if let Some((received, sent)) = metrics.network {
+            parts.push(format!("↓{}/s ↑{}/s", metrics::format_bytes(received), metrics::format_bytes(sent)));
+        }
+        // Disks last, as the line is cut at the edge of the screen
+        for (mount, usage) in &metrics.disks {
+            parts.push(format!("{} {}", metrics::short_mount(mount, 12), percent(*usage)));
+        }
+        parts.join(" | ")
+    }
+
+    /// The keys for a running job or batch, e.g. ` - x/Ctrl-C to cancel,
+    /// p for progress`; `shows` is what the progress key opens.
+    fn running_hint(&self, shows: &str) -> String {
+        let mut hints = Vec::new();
+        let cancel = self.keymap.keys(KeyMode::Normal, Action::Cancel);
+        if !cancel.is_empty() {
+            hints.push(format!("{} to cancel", cancel));
+        }
+        let progress = self.keymap.keys(KeyMode::Normal, Action::Progress);
+        if !progress.is_empty() {
+            hints.push(format!("{} for {}", progress, shows));
+        }
+        if hints.is_empty() {
+            String::new()
+        } else {
+            format!(" - {}", hints.join(", "))
+        }
+    }
+
+    /// The main screen's key bar, what `{keys}` stands for in the footer.
+    fn main_key_bar(&self) -> String {
+        if self.pane.is_some() {
+            self.keymap.bar(
+                KeyMode::Normal,
+                &[
+                    (&[Action::Quit], "quit"),
+                    (&[Action::Down, Action::Up], "↕"),
+                    (&[Action::Launch], "launch"),
+                    (&[Action::FocusPane], "pane"),
+                    (&[Action::TogglePane], "background"),
+                    (&[Action::Cancel], "cancel"),
+                    (&[Action::Host], "host"),
+                    (&[Action::Add], "add"),
+                    (&[Action::Delete], "delete"),
+                    (&[Action::Help], "help"),
+                ],
+            )
+        } else {
+            self.keymap.bar(
+                KeyMode::Normal,
+                &[
+                    (&[Action::Quit], "quit"),
+                    (&[Action::Down, Action::Up], "↕"),
+                    (&[Action::Launch], "launch"),
+                    (&[Action::Cancel], "cancel"),
+                    (&[Action::Host], "host"),
+                    (&[Action::Add], "add"),
+                    (&[Action::Delete], "delete"),
+                    (&[Action::Help], "help"),
+                    (&[Action::Reload], "reload"),
+                ],
+            )
+        }
+    }
+
+    /// The `:` prompt in place of the bottom bar, with the completions
+    /// above it while cycling through them.
+    fn draw_command_line(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        let line = &self.command_line;
+        if let Some(selected) = line.completion_index.filter(|_| line.completions.len() > 1) {
+            queue!(out, MoveTo(0, height - 2), Clear(ClearType::CurrentLine))?;
+            for (i, completion) in line.completions.iter().enumerate() {
+                let word = completion.rsplit(' ').next().unwrap_or(completion);
+                if i == selected {
+                    queue!(
+                        out,
+                        self.theme.selected,
+                        Print(word),
+                        ResetColor,
+                        Print("  ")
+                    )?;
+                } else {
+                    queue!(out, Print(word), Print("  "))?;
+                }
+            }
+        }
+
+        queue!(
+            out,
+            MoveTo(0, height - 1),
+            Print(text::fit(&format!(":{}_", line.input), width as usize))
+        )?;
+        Ok(())
+    }
+
+    fn draw_pane(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        let Some((display_name, pane)) = &self.pane else {
+            return Ok(());
+        };
+        let Some((x, y, _, rows)) = Self::pane_area((width, height)) else {
+            return Ok(());
+        };
+        let focused = self.focus == Focus::Pane;
+
+        // Title line doubles as the separator from the program list
+        let title = match self.keymap.keys(KeyMode::Normal, Action::FocusPane) {
+            _ if focused => format!(" {} [focused]", display_name),
+            keys if keys.is_empty() => format!(" {}", display_name),
+            keys => format!(" {} [{} to focus]", display_name, keys),
+        };
+        queue!(
+            out,
+            MoveTo(0, y - 1),
+            if focused { self.theme.header } else { self.theme.footer },
+            Print(text::fit(&title, width as usize)),
+            ResetColor
+        )?;
+
+        let screen = pane.screen();
+        let cursor = if focused { screen.cursor() } else { None };
+
+        for row in 0..screen.size().0.min(rows as usize) {
+            queue!(out, MoveTo(x, y + row as u16))?;
+            let mut current = None;
+            for (col, cell) in screen.row(row).iter().enumerate() {
+                let mut style = cell.style;
+                // Draw the cursor as an inverted cell
+                style.reverse ^= cursor == Some((row, col));
+                if current != Some(style) {
+                    queue!(out, style)?;
+                    current = Some(style);
+                }
+                queue!(out, Print(cell))?;
+            }
+            queue!(out, SetAttribute(Attribute::Reset), ResetColor)?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_add_program_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        // Draw top bar
+        queue!(
+            out,
+            MoveTo(0, 0),
+            self.theme.form_header,
+            Print(text::fit(" Add New Program", width as usize)),
+            ResetColor
+        )?;
+
+        let start_y = 3;
+
+        // Draw form
+        queue!(
+            out,
+            MoveTo(2, start_y),
+            Print(format!("Step {} of 7: {}", self.add_form.step + 1, self.add_form.current_field()))
+        )?;
+
+        queue!(
+            out,
+            MoveTo(2, start_y + 2),
+            Print(format!("> {}", self.add_form.current_value()))
+        )?;
+
+        if self.add_form.step == 7 {
+            // Review step
+            queue!(out, MoveTo(2, start_y + 4), Print("Review:"))?;
+            queue!(out, MoveTo(4, start_y + 5), Print(format!("Name: {}", self.add_form.name)))?;
+            queue!(out, MoveTo(4, start_y + 6), Print(format!("Display: {}", self.add_form.display_name)))?;
+            queue!(out, MoveTo(4, start_y + 7), Print(format!("Command: {}", self.add_form.command)))?;
+            if !self.add_form.args.is_empty() {
+                queue!(out, MoveTo(4, start_y + 8), Print(format!("Args: {}", self.add_form.args)))?;
+            }
+            if !self.add_form.description.is_empty() {
+                queue!(out, MoveTo(4, start_y + 9), Print(format!("Description: {}", self.add_form.description)))?;
+            }
+            queue!(out, MoveTo(4, start_y + 10), Print(format!("Run with sudo: {}", if self.add_form.run_with_sudo { "Yes" } else { "No" })))?;
+            queue!(out, MoveTo(4, start_y + 11), Print(format!("Show output: {}", if self.add_form.show_output { "Yes" } else { "No" })))?;
+            queue!(out, MoveTo(2, start_y + 13), Print("Press Enter to save, Esc to cancel"))?;
+        }
+
+        // Draw bottom bar
+        let help_text = "Enter:next | Esc:cancel | Type to input";
+        queue!(
+            out,
+            MoveTo(0, height - 1),
+            self.theme.footer,
+            Print(text::fit(help_text, width as usize)),
+            ResetColor
+        )?;
+
+        Ok(())
+    }
+
+    fn draw_help_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        // Draw top bar
+        queue!(
+            out,
+            MoveTo(0, 0),
+            self.theme.help_header,
+            Print(text::fit(" Help - RDash", width as usize)),
+            ResetColor
+        )?;
+
+        let mut help_lines = vec![String::new(), "RDash - Vim-like Server Dashboard".to_string(), String::new()];
+        help_lines.extend(self.keymap.help_lines());
+        help_lines.extend([
+            "CONFIGURATION:".to_string(),
+            "  Config file: ~/.config/rdash/config.json".to_string(),
+            "  You can edit this file manually to modify programs and keys".to_string(),
+            String::new(),
+            format!("Press {} to return...", self.keymap.keys(KeyMode::Help, Action::Close)),
+        ]);
+
+        // Calculate center position for content
+        let content_width = help_lines.iter().map(|line| text::width(line)).max().unwrap_or(0);
+        let start_x = text::centered(content_width, width, 2);
+
+        for (i, line) in help_lines.iter().enumerate() {
+            if i + 2 < height as usize {
+                queue!(
+                    out,
+                    MoveTo(start_x, 2 + i as u16),
+                    Print(text::truncate(line, width.saturating_sub(start_x) as usize))
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn state_marker(&self, state: StepState) -> (&'static str, Style) {
+        let theme = &self.theme;
+        match state {
+            StepState::Pending => ("[    ]", theme.dim),
+            StepState::Running => ("[ .. ]", theme.warning),
+            StepState::Succeeded => ("[ OK ]", theme.success),
+            StepState::Failed => ("[FAIL]", theme.error),
+            StepState::Killed => ("[KILL]", theme.error),
+            StepState::TimedOut => ("[TIME]", theme.error),
+            StepState::Skipped => ("[SKIP]", theme.dim),
+            StepState::NotReady => ("[DOWN]", theme.warning),
+            StepState::Satisfied => ("[ UP ]", theme.success),
+        }
+    }
+
+    fn draw_batch_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        let Some((display_name, batch)) = &self.batch else {
+            return Ok(());
+        };
+        let items = batch.items();
+
+        // Draw top bar
+        let title = format!(
+            " {}: {} ({}/{} done)",
+            self.batch_kind.title(),
+            display_name,
+            batch.completed(),
+            items.len()
+        );
+        queue!(
+            out,
+            MoveTo(0, 0),
+            self.theme.panel_header,
+            Print(text::fit(&title, width as usize)),
+            ResetColor
+        )?;
 
-                // Restore terminal state
-                terminal::enable_raw_mode()?;
-                execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        let (name_heading, detail_heading) = self.batch_kind.headings();
+        let name_width = items.iter().map(|item| text::width(&item.name)).max().unwrap_or(0).max(name_heading.len());
+        let detail_width = items.iter().map(|item| text::width(&item.detail)).max().unwrap_or(0).max(detail_heading.len());
+        queue!(
+            out,
+            MoveTo(2, 2),
+            self.theme.dim,
+            Print(format!("{}  {}  STATE   TIME", text::fit(name_heading, name_width), text::fit(detail_heading, detail_width))),
+            ResetColor
+        )?;
 
-                match result {
-                    Ok(status) => {
-                        if status.success() {
-                            self.status_message = Some(format!("Executed: {}", program.display_name));
-                        } else {
-                            self.status_message = Some(format!("Failed to execute: {}", program.display_name));
-                        }
-                    }
-                    Err(e) => {
-                        self.status_message = Some(format!("Error launching {}: {}", program.display_name, e));
-                    }
-                }
+        for (i, item) in items.iter().enumerate() {
+            let y = 3 + i as u16;
+            if y >= height.saturating_sub(2) {
+                break;
             }
-        }
-        Ok(())
-    }
 
-    fn delete_selected_program(&mut self) -> io::Result<()> {
-        let programs = self.config.get_programs();
-        if let Some(program) = programs.get(self.selected_index) {
-            let name = program.name.clone();
-            let display_name = program.display_name.clone();
-            
-            if self.config.remove_program(&name) {
-                if let Err(e) = self.config.save() {
-                    self.status_message = Some(format!("Error saving config: {}", e));
-                } else {
-                    self.status_message = Some(format!("Deleted: {}", display_name));
-                    // Adjust selected index if necessary
-                    let new_len = self.config.get_programs().len();
-                    if new_len > 0 && self.selected_index >= new_len {
-                        self.selected_index = new_len - 1;
-                    }
-                }
+            let (marker, style) = self.state_marker(item.state);
+            let elapsed = item.elapsed.or_else(|| batch.running_elapsed(i));
+            let time = elapsed.map_or_else(String::new, |e| format!("{}s", e.as_secs()));
+            let row = format!("{}  {}  ", text::fit(&item.name, name_width), text::fit(&item.detail, detail_width));
+
+            if i == self.batch_index {
+                queue!(
+                    out,
+                    MoveTo(2, y),
+                    self.theme.selected,
+                    Print(&row),
+                    Print(marker),
+                    Print(format!("  {}", time)),
+                    ResetColor
+                )?;
+            } else {
+                queue!(
+                    out,
+                    MoveTo(2, y),
+                    Print(&row),
+                    style,
+                    Print(marker),
+                    ResetColor,
+                    Print(format!("  {}", time))
+                )?;
             }
         }
+
+        // Draw bottom bar
+        let help_text = format!(
+            " {}",
+            self.keymap.bar(
+                KeyMode::Batch,
+                &[
+                    (&[Action::Down, Action::Up], "↕"),
+                    (&[Action::Open], "show output"),
+                    (&[Action::Cancel], "cancel"),
+                    (&[Action::Close], "close"),
+                ],
+            )
+        );
+        queue!(
+            out,
+            MoveTo(0, height - 1),
+            self.theme.footer,
+            Print(text::fit(&help_text, width as usize)),
+            ResetColor
+        )?;
+
         Ok(())
     }
 
-    fn save_new_program(&mut self) -> io::Result<()> {
-        let args: Vec<String> = if self.add_form.args.is_empty() {
-            vec![]
-        } else {
-            self.add_form.args.split_whitespace().map(|s| s.to_string()).collect()
-        };
+    fn draw_selection_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        let menu = &self.selection_menu;
 
-        let entry = ProgramEntry {
-            name: self.add_form.name.clone(),
-            display_name: self.add_form.display_name.clone(),
-            command: self.add_form.command.clone(),
-            args,
-            description: if self.add_form.description.is_empty() {
-                None
+        // Draw top bar
+        let title = format!(" Batch actions: {} programs", menu.names.len());
+        queue!(
+            out,
+            MoveTo(0, 0),
+            self.theme.panel_header,
+            Print(text::fit(&title, width as usize)),
+            ResetColor
+        )?;
+
+        for (i, action) in SelectionAction::ALL.iter().enumerate() {
+            let y = 2 + i as u16;
+            if i == menu.index && menu.prompt.is_none() {
+                queue!(
+                    out,
+                    MoveTo(2, y),
+                    self.theme.selected,
+                    Print(action.label()),
+                    ResetColor
+                )?;
             } else {
-                Some(self.add_form.description.clone())
-            },
-            run_with_sudo: self.add_form.run_with_sudo,
-            show_output: self.add_form.show_output,
-        };
+                queue!(out, MoveTo(2, y), Print(action.label()))?;
+            }
+        }
 
-        self.config.add_program(entry);
-        
-        if let Err(e) = self.config.save() {
-            self.status_message = Some(format!("Error saving config: {}", e));
-        } else {
-            self.status_message = Some(format!("Added: {}", self.add_form.display_name));
+        let y = 3 + SelectionAction::ALL.len() as u16;
+        let names: Vec<String> = self.menu_programs().into_iter().map(|p| p.display_name).collect();
+        let programs = text::truncate(&format!("Programs: {}", names.join(", ")), (width as usize).saturating_sub(4));
+        queue!(
+            out,
+            MoveTo(2, y),
+            self.theme.dim,
+            Print(programs),
+            ResetColor
+        )?;
+
+        if let Some((action, input)) = &menu.prompt {
+            let prompt = match action {
+                SelectionAction::Delete => format!("Delete {} programs?", menu.names.len()),
+                SelectionAction::Group => format!("Group (empty removes it): {}_", input),
+                _ => format!("Export to: {}_", input),
+            };
+            queue!(
+                out,
+                MoveTo(2, y + 2),
+                self.theme.warning,
+                Print(prompt),
+                ResetColor
+            )?;
         }
 
-        self.add_form.reset();
+        // Draw bottom bar
+        let help_text = match &menu.prompt {
+            Some((SelectionAction::Delete, _)) => match self.keymap.bar(KeyMode::Picker, &[(&[Action::Confirm], "delete")]) {
+                confirm if confirm.is_empty() => " any key:cancel".to_string(),
+                confirm => format!(" {} | any other key:cancel", confirm),
+            },
+            Some(_) => " Enter:apply | Esc:back".to_string(),
+            None => format!(
+                " {}",
+                self.keymap.bar(
+                    KeyMode::Picker,
+                    &[(&[Action::Down, Action::Up], "↕"), (&[Action::Select], "select"), (&[Action::Close], "cancel")],
+                )
+            ),
+        };
+        queue!(
+            out,
+            MoveTo(0, height - 1),
+            self.theme.footer,
+            Print(text::fit(&help_text, width as usize)),
+            ResetColor
+        )?;
+
         Ok(())
     }
 
-    fn reload_config(&mut self) -> io::Result<()> {
-        match Config::load() {
-            Ok(config) => {
-                self.config = config;
-                self.selected_index = 0;
-                self.status_message = Some("Configuration reloaded".to_string());
+    fn draw_host_switcher_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        // Draw top bar
+        queue!(
+            out,
+            MoveTo(0, 0),
+            self.theme.panel_header,
+            Print(text::fit(" Select Host", width as usize)),
+            ResetColor
+        )?;
+
+        let rows: Vec<(String, String, String)> = self
+            .host_choices()
+            .into_iter()
+            .map(|choice| match choice {
+                None => ("local".to_string(), "this machine".to_string(), String::new()),
+                Some(name) => {
+                    let tags = self.config.hosts.get(&name).map_or_else(String::new, |h| h.tags.join(", "));
+                    let address = self.config.resolve_host(&name).address();
+                    (name, address, tags)
+                }
+            })
+            .collect();
+        let name_width = rows.iter().map(|(name, _, _)| text::width(name)).max().unwrap_or(0);
+        let address_width = rows.iter().map(|(_, address, _)| text::width(address)).max().unwrap_or(0);
+        let active = self.host_choices().iter().position(|h| *h == self.active_host);
+
+        for (i, (name, address, tags)) in rows.iter().enumerate() {
+            let y = 2 + i as u16;
+            if y >= height.saturating_sub(2) {
+                break;
             }
-            Err(e) => {
-                self.status_message = Some(format!("Error reloading config: {}", e));
+
+            let marker = if Some(i) == active { "*" } else { " " };
+            let row = format!("{} {}  {}  {}", marker, text::fit(name, name_width), text::fit(address, address_width), tags);
+            if i == self.host_index {
+                queue!(
+                    out,
+                    MoveTo(2, y),
+                    self.theme.selected,
+                    Print(&row),
+                    ResetColor
+                )?;
+            } else {
+                queue!(out, MoveTo(2, y), Print(&row))?;
             }
         }
+
+        // Draw bottom bar
+        let help_text = format!(
+            " {}",
+            self.keymap.bar(
+                KeyMode::Picker,
+                &[(&[Action::Down, Action::Up], "↕"), (&[Action::Select], "select"), (&[Action::Close], "cancel")],
+            )
+        );
+        queue!(
+            out,
+            MoveTo(0, height - 1),
+            self.theme.footer,
+            Print(text::fit(&help_text, width as usize)),
+            ResetColor
+        )?;
+
         Ok(())
     }
 
-    fn draw(&mut self) -> io::Result<()> {
-        let (width, height) = terminal::size()?;
-        
-        // Clear screen
-        execute!(io::stdout(), Clear(ClearType::All))?;
+    fn draw_container_picker_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        let Some((program, containers)) = &self.container_pick else {
+            return Ok(());
+        };
 
-        match self.mode {
-            Mode::Normal => self.draw_main_screen(width, height)?,
-            Mode::AddProgram => self.draw_add_program_screen(width, height)?,
-            Mode::Help => self.draw_help_screen(width, height)?,
-            Mode::ShowOutput => self.draw_output_screen(width, height)?,
+        // Draw top bar
+        let title = format!(" Select Container: {}", program.display_name);
+        queue!(
+            out,
+            MoveTo(0, 0),
+            self.theme.panel_header,
+            Print(text::fit(&title, width as usize)),
+            ResetColor
+        )?;
+
+        let name_width = containers.iter().map(|c| c.name.len()).max().unwrap_or(0).max(4);
+        let image_width = containers.iter().map(|c| c.image.len()).max().unwrap_or(0).max(5);
+        queue!(
+            out,
+            MoveTo(2, 2),
+            self.theme.dim,
+            Print(format!("{:name_width$}  {:image_width$}  STATUS", "NAME", "IMAGE")),
+            ResetColor
+        )?;
+
+        for (i, container) in containers.iter().enumerate() {
+            let y = 3 + i as u16;
+            if y >= height.saturating_sub(2) {
+                break;
+            }
+
+            let row = format!("{:name_width$}  {:image_width$}  {}", container.name, container.image, container.status);
+            if i == self.container_index {
+                queue!(
+                    out,
+                    MoveTo(2, y),
+                    self.theme.selected,
+                    Print(&row),
+                    ResetColor
+                )?;
+            } else {
+                queue!(out, MoveTo(2, y), Print(&row))?;
+            }
         }
 
-        io::stdout().flush()?;
+        // Draw bottom bar
+        let help_text = format!(
+            " {}",
+            self.keymap.bar(
+                KeyMode::Picker,
+                &[(&[Action::Down, Action::Up], "↕"), (&[Action::Select], "launch"), (&[Action::Close], "cancel")],
+            )
+        );
+        queue!(
+            out,
+            MoveTo(0, height - 1),
+            self.theme.footer,
+            Print(text::fit(&help_text, width as usize)),
+            ResetColor
+        )?;
+
         Ok(())
     }
 
-    fn draw_main_screen(&self, width: u16, height: u16) -> io::Result<()> {
+    fn draw_systemd_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        let units = self.visible_units();
+
         // Draw top bar
-        execute!(
-            io::stdout(),
+        let mut title = format!(" Systemd Units ({})", units.len());
+        if self.failed_units_only {
+            title.push_str(" - failed only");
+        }
+        if matches!(self.listing, Some((Listing::Units, _))) {
+            title.push_str(" - loading...");
+        }
+        if let Some(host) = &self.active_host {
+            title = format!("{} [@{}]", title, host);
+        }
+        queue!(
+            out,
             MoveTo(0, 0),
-            SetBackgroundColor(Color::Blue),
-            SetForegroundColor(Color::White),
-            Print(format!("{:width$}", " RDash - Server Dashboard", width = width as usize)),
+            self.theme.panel_header,
+            Print(text::fit(&title, width as usize)),
             ResetColor
         )?;
 
-        // Draw programs list
-        let programs = self.config.get_programs();
-        let start_y = 2;
-        let content_height = height.saturating_sub(4); // Leave space for header and footer
-
-        // Draw Sartre quote
-        let quote = "\"L'homme est condamné à être libre.\" - Sartre";
-        let quote_x = if width as usize > quote.len() {
-            (width as usize - quote.len()) / 2
-        } else {
-            2
-        } as u16;
-        
-        execute!(
-            io::stdout(),
-            MoveTo(quote_x, start_y),
-            SetForegroundColor(Color::DarkGrey),
-            Print(quote),
+        let name_width = units.iter().map(|unit| unit.name.len()).max().unwrap_or(0).max(4);
+        queue!(
+            out,
+            MoveTo(2, 2),
+            self.theme.dim,
+            Print(format!("{:name_width$}  {:8}  {:10}  DESCRIPTION", "UNIT", "ACTIVE", "SUB")),
             ResetColor
         )?;
 
-        let programs_start_y = start_y + 2;
+        // Keep the selection in view
+        let rows = height.saturating_sub(6) as usize;
+        let offset = (self.unit_index + 1).saturating_sub(rows);
+        for (i, unit) in units.iter().enumerate().skip(offset).take(rows) {
+            let y = 3 + (i - offset) as u16;
+            let style = match unit.active.as_str() {
+                "active" => self.theme.success,
+                "failed" => self.theme.error,
+                "inactive" => self.theme.dim,
+                _ => self.theme.warning,
+            };
+            let name = format!("{:name_width$}  ", unit.name);
+            let state = format!("{:8}", unit.active);
+            let rest = format!("  {:10}  {}", unit.sub, unit.description);
 
-        if programs.is_empty() {
-            let empty_message = "No programs configured. Press 'a' to add a program.";
-            let start_x = if width as usize > empty_message.len() { 
-                (width as usize - empty_message.len()) / 2 
-            } else { 
-                2 
-            } as u16;
-            
-            execute!(
-                io::stdout(),
-                MoveTo(start_x, programs_start_y + 2),
-                Print(empty_message)
-            )?;
-        } else {
-            // Calculate the maximum width needed for centering
-            let max_program_width = programs.iter().map(|program| {
-                let sudo_indicator = if program.run_with_sudo { " [SUDO]" } else { "" };
-                let output_indicator = if program.show_output { " [OUT]" } else { "" };
-                let display_text = if let Some(ref desc) = program.description {
-                    format!("[ {}{}{} - {} ]", program.display_name, sudo_indicator, output_indicator, desc)
-                } else {
-                    format!("[ {}{}{} ]", program.display_name, sudo_indicator, output_indicator)
-                };
-                display_text.len()
-            }).max().unwrap_or(0);
-
-            let start_x = if width as usize > max_program_width { 
-                (width as usize - max_program_width) / 2 
-            } else { 
-                2 
-            } as u16;
-
-            for (i, program) in programs.iter().enumerate() {
-                if i < content_height as usize {
-                    let y = programs_start_y + i as u16;
-                    let is_selected = i == self.selected_index;
-
-                    let sudo_indicator = if program.run_with_sudo { " [SUDO]" } else { "" };
-                    let output_indicator = if program.show_output { " [OUT]" } else { "" };
-                    let display_text = if let Some(ref desc) = program.description {
-                        format!("[ {}{}{} - {} ]", program.display_name, sudo_indicator, output_indicator, desc)
-                    } else {
-                        format!("[ {}{}{} ]", program.display_name, sudo_indicator, output_indicator)
-                    };
-
-                    if is_selected {
-                        execute!(
-                            io::stdout(),
-                            MoveTo(start_x, y),
-                            SetBackgroundColor(Color::Yellow),
-                            SetForegroundColor(Color::Black),
-                            Print(&display_text),
-                            ResetColor
-                        )?;
-                    } else {
-                        execute!(
-                            io::stdout(),
-                            MoveTo(start_x, y),
-                            Print(&display_text)
-                        )?;
-                    }
-                }
+            if i == self.unit_index {
+                queue!(
+                    out,
+                    MoveTo(2, y),
+                    self.theme.selected,
+                    Print(&name),
+                    Print(&state),
+                    Print(&rest),
+                    ResetColor
+                )?;
+            } else {
+                queue!(
+                    out,
+                    MoveTo(2, y),
+                    Print(&name),
+                    style,
+                    Print(&state),
+                    ResetColor,
+                    Print(&rest)
+                )?;
             }
         }
 
-        // Draw status message if any
         if let Some(ref message) = self.status_message {
-            execute!(
-                io::stdout(),
-                MoveTo(2, height - 3),
-                SetForegroundColor(Color::Green),
+            queue!(
+                out,
+                MoveTo(2, height - 2),
+                self.theme.warning,
                 Print(message),
                 ResetColor
             )?;
         }
 
         // Draw bottom bar
-        let help_text = "q:quit | j/k:↕ | Enter:launch | a:add | d:delete | h:help | r:reload";
-        execute!(
-            io::stdout(),
+        let help_text = format!(
+            " {}",
+            self.keymap.bar(
+                KeyMode::Systemd,
+                &[
+                    (&[Action::Down, Action::Up], "↕"),
+                    (&[Action::Status], "status"),
+                    (&[Action::Logs], "logs"),
+                    (&[Action::Start], "start"),
+                    (&[Action::Stop], "stop"),
+                    (&[Action::Restart], "restart"),
+                    (&[Action::FailedOnly], "failed only"),
+                    (&[Action::Refresh], "refresh"),
+                    (&[Action::Pin], "pin"),
+                    (&[Action::Close], "close"),
+                ],
+            )
+        );
+        queue!(
+            out,
             MoveTo(0, height - 1),
-            SetBackgroundColor(Color::DarkGrey),
-            SetForegroundColor(Color::White),
-            Print(format!("{:width$}", help_text, width = width as usize)),
+            self.theme.footer,
+            Print(text::fit(&help_text, width as usize)),
             ResetColor
         )?;
 
         Ok(())
     }
 
-    fn draw_add_program_screen(&self, width: u16, height: u16) -> io::Result<()> {
+    fn draw_sudo_prompt_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        let Some(prompt) = &self.sudo_prompt else {
+            return Ok(());
+        };
+
         // Draw top bar
-        execute!(
-            io::stdout(),
+        queue!(
+            out,
             MoveTo(0, 0),
-            SetBackgroundColor(Color::Green),
-            SetForegroundColor(Color::White),
-            Print(format!("{:width$}", " Add New Program", width = width as usize)),
+            self.theme.help_header,
+            Print(text::fit(" sudo Authentication", width as usize)),
             ResetColor
         )?;
 
-        let start_y = 3;
+        let reason = match &prompt.then {
+            Some(program) => format!("{} runs commands with sudo.", program.display_name),
+            None => "Cache sudo credentials for later runs.".to_string(),
+        };
+        let user = std::env::var("USER").unwrap_or_default();
+        queue!(
+            out,
+            MoveTo(2, 2),
+            Print(reason),
+            MoveTo(2, 4),
+            Print(format!("[sudo] password for {}: {}", user, "*".repeat(prompt.password.chars().count())))
+        )?;
 
-        // Draw form
-        execute!(
-            io::stdout(),
-            MoveTo(2, start_y),
-            Print(format!("Step {} of 7: {}", self.add_form.step + 1, self.add_form.current_field()))
+        if let Some(error) = &prompt.error {
+            queue!(
+                out,
+                MoveTo(2, 6),
+                self.theme.error,
+                Print(error),
+                ResetColor
+            )?;
+        }
+
+        // Draw bottom bar
+        let help_text = " Enter:authenticate | Esc:cancel";
+        queue!(
+            out,
+            MoveTo(0, height - 1),
+            self.theme.footer,
+            Print(text::fit(help_text, width as usize)),
+            ResetColor
         )?;
 
-        execute!(
-            io::stdout(),
-            MoveTo(2, start_y + 2),
-            Print(format!("> {}", self.add_form.current_value()))
+        Ok(())
+    }
+
+    fn draw_progress_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        let Some((display_name, _, job)) = &self.job else {
+            return Ok(());
+        };
+
+        // Draw top bar
+        queue!(
+            out,
+            MoveTo(0, 0),
+            self.theme.panel_header,
+            Print(text::fit(&format!(" Running: {}", display_name), width as usize)),
+            ResetColor
         )?;
 
-        if self.add_form.step == 7 {
-            // Review step
-            execute!(io::stdout(), MoveTo(2, start_y + 4), Print("Review:"))?;
-            execute!(io::stdout(), MoveTo(4, start_y + 5), Print(format!("Name: {}", self.add_form.name)))?;
-            execute!(io::stdout(), MoveTo(4, start_y + 6), Print(format!("Display: {}", self.add_form.display_name)))?;
-            execute!(io::stdout(), MoveTo(4, start_y + 7), Print(format!("Command: {}", self.add_form.command)))?;
-            if !self.add_form.args.is_empty() {
-                execute!(io::stdout(), MoveTo(4, start_y + 8), Print(format!("Args: {}", self.add_form.args)))?;
+        let current = job.current_step();
+        for (i, step) in job.steps().iter().enumerate() {
+            let y = 2 + i as u16;
+            if y >= height.saturating_sub(2) {
+                break;
             }
-            if !self.add_form.description.is_empty() {
-                execute!(io::stdout(), MoveTo(4, start_y + 9), Print(format!("Description: {}", self.add_form.description)))?;
-            }
-            execute!(io::stdout(), MoveTo(4, start_y + 10), Print(format!("Run with sudo: {}", if self.add_form.run_with_sudo { "Yes" } else { "No" })))?;
-            execute!(io::stdout(), MoveTo(4, start_y + 11), Print(format!("Show output: {}", if self.add_form.show_output { "Yes" } else { "No" })))?;
-            execute!(io::stdout(), MoveTo(2, start_y + 13), Print("Press Enter to save, Esc to cancel"))?;
+
+            let (marker, style) = self.state_marker(step.state);
+            let elapsed = match current {
+                Some((running, elapsed)) if running == i => format!(" ({}s)", elapsed.as_secs()),
+                _ => String::new(),
+            };
+
+            queue!(
+                out,
+                MoveTo(2, y),
+                style,
+                Print(marker),
+                ResetColor,
+                Print(format!(" {}{}", step.label, elapsed))
+            )?;
         }
 
         // Draw bottom bar
-        let help_text = "Enter:next | Esc:cancel | Type to input";
-        execute!(
-            io::stdout(),
+        let help_text = format!(
+            " {}",
+            self.keymap.bar(
+                KeyMode::Progress,
+                &[(&[Action::Cancel], "cancel"), (&[Action::Close], "hide (keeps running)")],
+            )
+        );
+        queue!(
+            out,
             MoveTo(0, height - 1),
-            SetBackgroundColor(Color::DarkGrey),
-            SetForegroundColor(Color::White),
-            Print(format!("{:width$}", help_text, width = width as usize)),
+            self.theme.footer,
+            Print(text::fit(&help_text, width as usize)),
             ResetColor
         )?;
 
         Ok(())
     }
 
-    fn draw_help_screen(&self, width: u16, height: u16) -> io::Result<()> {
+    fn draw_output_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        let Some(view) = &self.output_view else {
+            return Ok(());
+        };
+
+        let rows = Self::output_rows((width, height));
+        let end = (view.top + rows).min(view.lines.len());
+        let mut title = format!(" Output: {}", view.title);
+        if view.lines.len() > rows {
+            title = format!("{} | lines {}-{} of {}", title, view.top + 1, end, view.lines.len());
+        }
+        if let Some(search) = &view.search {
+            title = format!("{} | search: /{}/", title, search.as_str());
+        }
+
+        let footer = match (&view.prompt, &view.message) {
+            (Some((kind, input)), _) => format!(" {}{}_", kind, input),
+            (None, Some(message)) => format!(" {}", message),
+            (None, None) => format!(
+                " {}",
+                self.keymap.bar(
+                    KeyMode::Output,
+                    &[
+                        (&[Action::Down, Action::Up], "scroll"),
+                        (&[Action::Search], "search"),
+                        (&[Action::NextMatch, Action::PreviousMatch], "next/previous"),
+                        (&[Action::Yank], "copy"),
+                        (&[Action::Close], "close"),
+                    ],
+                )
+            ),
+        };
+
+        // Matches are shown reversed, those on the line moved to last in
+        // the selection style
+        let lines: Vec<Vec<Span>> = (view.top..end)
+            .map(|i| match &view.search {
+                Some(search) => {
+                    let ranges: Vec<_> =
+                        search.find_iter(&view.texts[i]).map(|m| m.range()).filter(|range| !range.is_empty()).collect();
+                    ansi::highlight(&view.lines[i], &ranges, |style| {
+                        if view.current == Some(i) {
+                            self.theme.selected.into()
+                        } else {
+                            vt::Style { reverse: !style.reverse, ..style }
+                        }
+                    })
+                }
+                None => view.lines[i].clone(),
+            })
+            .collect();
+
+        self.draw_text_box(out, width, height, &title, &lines, &footer)
+    }
+
+    fn draw_logs_screen(&self, out: &mut Screen, width: u16, height: u16) -> io::Result<()> {
+        let Some(view) = &self.log_view else {
+            return Ok(());
+        };
+
+        let state = if view.ended {
+            "ended"
+        } else if view.paused_at.is_some() {
+            "paused"
+        } else {
+            "following"
+        };
+        let mut title = format!(" Logs: {} - {}", view.title, state);
+        if let Some(filter) = &view.filter {
+            title = format!("{} | filter: /{}/", title, filter.as_str());
+        }
+
+        let footer = match (&view.filter_input, &view.filter_error) {
+            (Some(input), Some(error)) => format!(" Filter: {}_  ({})", input, error),
+            (Some(input), None) => format!(" Filter: {}_  (Enter:apply, empty clears | Esc:cancel)", input),
+            (None, _) => format!(
+                " {}",
+                self.keymap.bar(
+                    KeyMode::Logs,
+                    &[
+                        (&[Action::Pause], "pause"),
+                        (&[Action::Down, Action::Up], "scroll"),
+                        (&[Action::Follow], "follow"),
+                        (&[Action::Filter], "filter"),
+                        (&[Action::Close], "close"),
+                    ],
+                )
+            ),
+        };
+
+        let lines = view.tail.lines();
+        let end = view.paused_at.map_or(lines.lines.len(), |at| at.saturating_sub(lines.dropped));
+        let matching: Vec<&str> = lines
+            .lines
+            .iter()
+            .take(end)
+            .map(String::as_str)
+            .filter(|line| view.filter.as_ref().is_none_or(|f| f.is_match(line)))
+            .collect();
+
+        // The newest lines that fit, moved up by the scroll offset
+        let content_height = height.saturating_sub(6) as usize;
+        let bottom = matching.len().saturating_sub(view.scroll.min(matching.len().saturating_sub(content_height)));
+        let top = bottom.saturating_sub(content_height);
+        // Lines the program left uncoloured take the colour of their level
+        let visible: Vec<Vec<Span>> = matching[top..bottom]
+            .iter()
+            .map(|line| {
+                let mut spans = ansi::parse(line).pop().unwrap_or_default();
+                if let Some(style) = logs::level_style(line, &self.theme) {
+                    for span in spans.iter_mut().filter(|span| span.style == vt::Style::default()) {
+                        span.style = style.into();
+                    }
+                }
+                spans
+            })
+            .collect();
+
+        self.draw_text_box(out, width, height, &title, &visible, &footer)
+    }
+
+    /// Draws a title bar, a bordered box with one line of text per row and
+    /// a footer bar. Lines are styled spans, from captured output parsed by
+    /// `ansi::parse`, and are cut short with "…" when they do not fit.
+    fn draw_text_box(
+        &self,
+        out: &mut Screen,
+        width: u16,
+        height: u16,
+        title: &str,
+        lines: &[Vec<Span>],
+        footer: &str,
+    ) -> io::Result<()> {
         // Draw top bar
-        execute!(
-            io::stdout(),
+        queue!(
+            out,
             MoveTo(0, 0),
-            SetBackgroundColor(Color::Magenta),
-            SetForegroundColor(Color::White),
-            Print(format!("{:width$}", " Help - RDash", width = width as usize)),
+            self.theme.panel_header,
+            Print(text::fit(title, width as usize)),
             ResetColor
         )?;
 
-        let help_lines = vec![
-            "",
-            "RDash - Vim-like Server Dashboard",
-            "",
-            "NAVIGATION:",
-            "  [ j ] [ ↓ ]        Move down",
-            "  [ k ] [ ↑ ]        Move up",
-            "  [ Enter ]          Launch selected program",
-            "",
-            "PROGRAM MANAGEMENT:",
-            "  [ a ]              Add new program",
-            "  [ d ]              Delete selected program",
-            "  [ r ]              Reload configuration",
-            "",
-            "OTHER:",
-            "  [ h ] [ F1 ]       Show this help",
-            "  [ q ] [ Esc ]      Quit",
-            "",
-            "CONFIGURATION:",
-            "  Config file: ~/.config/rdash/config.json",
-            "  You can edit this file manually to modify programs",
-            "",
-            "Press any key to return...",
-        ];
+        // Draw output box border
+        let box_width = width.saturating_sub(4);
+        let box_height = height.saturating_sub(4);
 
-        // Calculate center position for content
-        let content_width = help_lines.iter().map(|line| line.len()).max().unwrap_or(0) as u16;
-        let start_x = if width > content_width { (width - content_width) / 2 } else { 2 };
+        // Top border
+        queue!(
+            out,
+            MoveTo(1, 1),
+            Print("┌"),
+            Print("─".repeat(box_width as usize - 2)),
+            Print("┐")
+        )?;
 
-        for (i, line) in help_lines.iter().enumerate() {
-            if i + 2 < height as usize {
-                execute!(
-                    io::stdout(),
-                    MoveTo(start_x, 2 + i as u16),
-                    Print(line)
-                )?;
+        // Bottom border
+        queue!(
+            out,
+            MoveTo(1, height - 2),
+            Print("└"),
+            Print("─".repeat(box_width as usize - 2)),
+            Print("┘")
+        )?;
+
+        // Side borders and content
+        let content_height = box_height.saturating_sub(2) as usize;
+
+        for i in 0..content_height {
+            queue!(out, MoveTo(1, 2 + i as u16), Print("│"))?;
+            queue!(out, MoveTo(box_width - 1, 2 + i as u16), Print("│"))?;
+
+            if let Some(line) = lines.get(i) {
+                let max_content_width = (box_width.saturating_sub(4)) as usize;
+                queue!(out, MoveTo(3, 2 + i as u16))?;
+                for span in ansi::truncate(line, max_content_width) {
+                    queue!(out, span.style, Print(&span.text))?;
+                }
+                queue!(out, ResetColor)?;
             }
         }
 
+        // Draw bottom instruction
+        queue!(
+            out,
+            MoveTo(0, height - 1),
+            self.theme.footer,
+            Print(text::fit(footer, width as usize)),
+            ResetColor
+        )?;
+
         Ok(())
     }
+}
 
-    fn draw_output_screen(&self, width: u16, height: u16) -> io::Result<()> {
-        if let Some((program_name, output)) = &self.output_data {
-            // Draw top bar
-            execute!(
-                io::stdout(),
-                MoveTo(0, 0),
-                SetBackgroundColor(Color::Cyan),
-                SetForegroundColor(Color::Black),
-                Print(format!("{:width$}", format!(" Output: {}", program_name), width = width as usize)),
-                ResetColor
-            )?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            // Draw output box border
-            let box_width = width.saturating_sub(4);
-            let box_height = height.saturating_sub(4);
-            
-            // Top border
-            execute!(
-                io::stdout(),
-                MoveTo(1, 1),
-                Print("┌"),
-                Print("─".repeat(box_width as usize - 2)),
-                Print("┐")
-            )?;
-            
-            // Bottom border
-            execute!(
-                io::stdout(),
-                MoveTo(1, height - 2),
-                Print("└"),
-                Print("─".repeat(box_width as usize - 2)),
-                Print("┘")
-            )?;
-            
-            // Side borders and content
-            let output_lines: Vec<&str> = output.lines().collect();
-            let content_height = box_height.saturating_sub(2) as usize;
-            
-            for i in 0..content_height {
-                execute!(io::stdout(), MoveTo(1, 2 + i as u16), Print("│"))?;
-                execute!(io::stdout(), MoveTo(box_width - 1, 2 + i as u16), Print("│"))?;
-                
-                if i < output_lines.len() {
-                    let line = output_lines[i];
-                    let max_content_width = (box_width.saturating_sub(4)) as usize;
-                    let display_line = if line.len() > max_content_width {
-                        format!("{}...", &line[0..max_content_width.saturating_sub(3)])
-                    } else {
-                        line.to_string()
-                    };
-                    execute!(io::stdout(), MoveTo(3, 2 + i as u16), Print(&display_line))?;
-                }
-            }
+    /// A dashboard over entries `a` to `e`, without reading any config file.
+    fn dashboard() -> Dashboard {
+        let programs: serde_json::Map<_, _> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|name| (name.to_string(), serde_json::json!({"name": name, "display_name": name, "command": "true"})))
+            .collect();
+        let config = serde_json::from_value(serde_json::json!({ "programs": programs })).unwrap();
+        Dashboard::with_config(config).unwrap()
+    }
 
-            // Draw bottom instruction
-            execute!(
-                io::stdout(),
-                MoveTo(0, height - 1),
-                SetBackgroundColor(Color::DarkGrey),
-                SetForegroundColor(Color::White),
-                Print(format!("{:width$}", " Press SPACE or ESC to close", width = width as usize)),
-                ResetColor
-            )?;
-        }
-        
-        Ok(())
+    #[test]
+    fn hints_follow_the_keymap() {
+        let mut dashboard = dashboard();
+        assert_eq!(dashboard.running_hint("progress"), " - x/Ctrl-C to cancel, p for progress");
+
+        let keys = HashMap::from([(
+            KeyMode::Normal,
+            HashMap::from([
+                ("x".to_string(), Action::None),
+                ("Ctrl-C".to_string(), Action::None),
+                ("K".to_string(), Action::Cancel),
+                ("p".to_string(), Action::None),
+            ]),
+        )]);
+        dashboard.keymap = Keymap::new(&keys, None).unwrap();
+        assert_eq!(dashboard.running_hint("results"), " - K to cancel");
+    }
+
+    #[test]
+    fn saturated_counts_stop_at_the_ends_of_the_list() {
+        let mut dashboard = dashboard();
+        dashboard.selected_index = 2;
+        dashboard.apply_motion(Motion::Down(usize::MAX)).unwrap();
+        assert_eq!(dashboard.selected_index, 4);
+        dashboard.apply_motion(Motion::Up(usize::MAX)).unwrap();
+        assert_eq!(dashboard.selected_index, 0);
+        dashboard.apply_motion(Motion::Line(usize::MAX)).unwrap();
+        assert_eq!(dashboard.selected_index, 4);
+    }
+
+    #[test]
+    fn saturated_counts_stay_in_the_jump_list() {
+        let mut dashboard = dashboard();
+        dashboard.apply_motion(Motion::Last).unwrap();
+        dashboard.apply_motion(Motion::First).unwrap();
+        dashboard.apply_motion(Motion::JumpBack(1)).unwrap();
+        assert_eq!(dashboard.selected_index, 4);
+
+        dashboard.apply_motion(Motion::JumpForward(usize::MAX)).unwrap();
+        assert_eq!(dashboard.selected_index, 4);
+        assert_eq!(dashboard.status_message.as_deref(), Some("At the end of the jump list"));
+        dashboard.apply_motion(Motion::JumpBack(usize::MAX)).unwrap();
+        assert_eq!(dashboard.selected_index, 4);
+        assert_eq!(dashboard.status_message.as_deref(), Some("At the start of the jump list"));
+        dashboard.apply_motion(Motion::JumpForward(1)).unwrap();
+        assert_eq!(dashboard.selected_index, 0);
+    }
+
+    #[test]
+    fn pane_fits_between_the_list_and_the_bottom_rows() {
+        assert_eq!(Dashboard::pane_area((80, 24)), Some((0, 9, 80, 12)));
+        // One row of program above the status line and the bottom bar
+        assert_eq!(Dashboard::pane_area((80, 11)), Some((0, 7, 80, 1)));
+    }
+
+    #[test]
+    fn pane_is_hidden_without_room() {
+        assert_eq!(Dashboard::pane_area((80, 10)), None);
+        assert_eq!(Dashboard::pane_area((80, 5)), None);
+        assert_eq!(Dashboard::pane_area((0, 0)), None);
     }
 }
@@ -0,0 +1,684 @@
+use crossterm::style::{Attribute, Color, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use crossterm::Command;
+use std::fmt;
+use std::io;
+use unicode_width::UnicodeWidthChar;
+
+/// What the right half of a wide character holds; the character itself is
+/// in the cell to its left.
+pub const WIDE_TAIL: char = '\0';
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fg: Color::Reset,
+            bg: Color::Reset,
+            bold: false,
+            underline: false,
+            reverse: false,
+        }
+    }
+}
+
+impl Style {
+    /// Applies the parameters of an SGR (`ESC [ ... m`) sequence.
+    pub fn apply_sgr(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            *self = Self::default();
+            return;
+        }
+
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                4 => self.underline = true,
+                7 => self.reverse = true,
+                22 => self.bold = false,
+                24 => self.underline = false,
+                27 => self.reverse = false,
+                n @ 30..=37 => self.fg = ansi_color(n - 30),
+                38 => {
+                    if let Some((color, used)) = extended_color(&params[i + 1..]) {
+                        self.fg = color;
+                        i += used;
+                    }
+                }
+                39 => self.fg = Color::Reset,
+                n @ 40..=47 => self.bg = ansi_color(n - 40),
+                48 => {
+                    if let Some((color, used)) = extended_color(&params[i + 1..]) {
+                        self.bg = color;
+                        i += used;
+                    }
+                }
+                49 => self.bg = Color::Reset,
+                n @ 90..=97 => self.fg = ansi_color(n - 90 + 8),
+                n @ 100..=107 => self.bg = ansi_color(n - 100 + 8),
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Sets exactly this style, whatever was set before.
+impl Command for Style {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        SetAttribute(Attribute::Reset).write_ansi(f)?;
+        SetForegroundColor(self.fg).write_ansi(f)?;
+        SetBackgroundColor(self.bg).write_ansi(f)?;
+        if self.bold {
+            SetAttribute(Attribute::Bold).write_ansi(f)?;
+        }
+        if self.underline {
+            SetAttribute(Attribute::Underlined).write_ansi(f)?;
+        }
+        if self.reverse {
+            SetAttribute(Attribute::Reverse).write_ansi(f)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn ansi_color(index: u16) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::DarkRed,
+        2 => Color::DarkGreen,
+        3 => Color::DarkYellow,
+        4 => Color::DarkBlue,
+        5 => Color::DarkMagenta,
+        6 => Color::DarkCyan,
+        7 => Color::Grey,
+        8 => Color::DarkGrey,
+        9 => Color::Red,
+        10 => Color::Green,
+        11 => Color::Yellow,
+        12 => Color::Blue,
+        13 => Color::Magenta,
+        14 => Color::Cyan,
+        15 => Color::White,
+        n => Color::AnsiValue(n as u8),
+    }
+}
+
+/// Parses the `5;n` or `2;r;g;b` tail of an SGR 38/48 parameter, returning
+/// the colour and how many parameters it consumed.
+fn extended_color(params: &[u16]) -> Option<(Color, usize)> {
+    match params {
+        [5, n, ..] => Some((ansi_color(*n & 0xff), 2)),
+        [2, r, g, b, ..] => Some((
+            Color::Rgb {
+                r: *r as u8,
+                g: *g as u8,
+                b: *b as u8,
+            },
+            4,
+        )),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub ch: char,
+    /// A combining mark drawn over the character, such as an accent.
+    pub mark: Option<char>,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            mark: None,
+            style: Style::default(),
+        }
+    }
+}
+
+impl Cell {
+    pub fn is_wide(&self) -> bool {
+        self.ch.width() == Some(2)
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ch == WIDE_TAIL {
+            return Ok(());
+        }
+        write!(f, "{}", self.ch)?;
+        match self.mark {
+            Some(mark) => write!(f, "{}", mark),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Ground,
+    Escape,
+    Csi,
+    Osc,
+    OscEscape,
+    Charset,
+}
+
+/// A minimal VT100/xterm screen: enough of the cursor, erase, scroll and
+/// SGR sequences to host full-screen tools like `htop` or `vim` in a pane.
+pub struct Screen {
+    rows: usize,
+    cols: usize,
+    grid: Vec<Vec<Cell>>,
+    primary: Option<Vec<Vec<Cell>>>,
+    cursor: (usize, usize),
+    saved_cursor: (usize, usize),
+    style: Style,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    wrap_pending: bool,
+    cursor_visible: bool,
+    app_cursor_keys: bool,
+    state: State,
+    params: String,
+    utf8: Vec<u8>,
+    replies: Vec<u8>,
+}
+
+impl Screen {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let rows = rows.max(1);
+        let cols = cols.max(1);
+        Self {
+            rows,
+            cols,
+            grid: vec![vec![Cell::default(); cols]; rows],
+            primary: None,
+            cursor: (0, 0),
+            saved_cursor: (0, 0),
+            style: Style::default(),
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            wrap_pending: false,
+            cursor_visible: true,
+            app_cursor_keys: false,
+            state: State::Ground,
+            params: String::new(),
+            utf8: Vec::new(),
+            replies: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn row(&self, row: usize) -> &[Cell] {
+        &self.grid[row]
+    }
+
+    /// Cursor position as (row, column), if the program wants it shown.
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor_visible.then_some(self.cursor)
+    }
+
+    pub fn app_cursor_keys(&self) -> bool {
+        self.app_cursor_keys
+    }
+
+    /// Answers to terminal queries (cursor position, device attributes)
+    /// that must be written back to the program.
+    pub fn take_replies(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.replies)
+    }
+
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        let rows = rows.max(1);
+        let cols = cols.max(1);
+        for grid in std::iter::once(&mut self.grid).chain(self.primary.as_mut()) {
+            grid.resize(rows, vec![Cell::default(); cols]);
+            for line in grid.iter_mut() {
+                line.resize(cols, Cell::default());
+            }
+        }
+        self.rows = rows;
+        self.cols = cols;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.cursor = (self.cursor.0.min(rows - 1), self.cursor.1.min(cols - 1));
+        self.wrap_pending = false;
+    }
+
+    pub fn process(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.process_byte(byte);
+        }
+    }
+
+    fn process_byte(&mut self, byte: u8) {
+        match self.state {
+            State::Ground => self.ground(byte),
+            State::Escape => self.escape(byte),
+            State::Csi => match byte {
+                0x1b => self.state = State::Escape,
+                0x30..=0x3f => self.params.push(byte as char),
+                0x40..=0x7e => {
+                    self.state = State::Ground;
+                    self.dispatch_csi(byte as char);
+                }
+                _ => {}
+            },
+            State::Osc => match byte {
+                0x07 => self.state = State::Ground,
+                0x1b => self.state = State::OscEscape,
+                _ => {}
+            },
+            State::OscEscape => self.state = State::Ground,
+            State::Charset => self.state = State::Ground,
+        }
+    }
+
+    fn ground(&mut self, byte: u8) {
+        if !self.utf8.is_empty() || byte >= 0x80 {
+            self.utf8.push(byte);
+            let expected = match self.utf8[0] {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            if self.utf8.len() >= expected {
+                let decoded = std::str::from_utf8(&self.utf8).ok().and_then(|s| s.chars().next());
+                self.utf8.clear();
+                self.put_char(decoded.unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            return;
+        }
+
+        match byte {
+            0x1b => self.state = State::Escape,
+            b'\r' => {
+                self.cursor.1 = 0;
+                self.wrap_pending = false;
+            }
+            b'\n' | 0x0b | 0x0c => self.linefeed(),
+            0x08 => {
+                self.cursor.1 = self.cursor.1.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            b'\t' => {
+                self.cursor.1 = ((self.cursor.1 / 8 + 1) * 8).min(self.cols - 1);
+                self.wrap_pending = false;
+            }
+            0x20..=0x7e => self.put_char(byte as char),
+            _ => {}
+        }
+    }
+
+    fn escape(&mut self, byte: u8) {
+        self.state = State::Ground;
+        match byte {
+            b'[' => {
+                self.params.clear();
+                self.state = State::Csi;
+            }
+            b']' => self.state = State::Osc,
+            b'(' | b')' | b'*' | b'+' => self.state = State::Charset,
+            b'7' => self.saved_cursor = self.cursor,
+            b'8' => self.move_to(self.saved_cursor.0, self.saved_cursor.1),
+            b'D' => self.linefeed(),
+            b'E' => {
+                self.cursor.1 = 0;
+                self.linefeed();
+            }
+            b'M' => self.reverse_index(),
+            b'c' => *self = Self::new(self.rows, self.cols),
+            _ => {}
+        }
+    }
+
+    fn dispatch_csi(&mut self, action: char) {
+        let private = self.params.starts_with('?');
+        let params: Vec<u16> = self
+            .params
+            .trim_start_matches(['?', '>', '<', '='])
+            .split(';')
+            .filter(|p| !p.is_empty())
+            .map(|p| p.parse().unwrap_or(0))
+            .collect();
+        // Count-style parameters treat a missing or zero value as one
+        let n = |i: usize| params.get(i).copied().filter(|&v| v > 0).unwrap_or(1) as usize;
+        let (row, col) = self.cursor;
+
+        match action {
+            'A' => self.move_to(row.saturating_sub(n(0)), col),
+            'B' | 'e' => self.move_to(row + n(0), col),
+            'C' | 'a' => self.move_to(row, col + n(0)),
+            'D' => self.move_to(row, col.saturating_sub(n(0))),
+            'E' => self.move_to(row + n(0), 0),
+            'F' => self.move_to(row.saturating_sub(n(0)), 0),
+            'G' | '`' => self.move_to(row, n(0) - 1),
+            'd' => self.move_to(n(0) - 1, col),
+            'H' | 'f' => self.move_to(n(0) - 1, n(1) - 1),
+            'J' => match params.first().copied().unwrap_or(0) {
+                0 => {
+                    self.erase_cells(row, col, self.cols);
+                    self.erase_rows(row + 1, self.rows);
+                }
+                1 => {
+                    self.erase_rows(0, row);
+                    self.erase_cells(row, 0, col + 1);
+                }
+                _ => self.erase_rows(0, self.rows),
+            },
+            'K' => match params.first().copied().unwrap_or(0) {
+                0 => self.erase_cells(row, col, self.cols),
+                1 => self.erase_cells(row, 0, col + 1),
+                _ => self.erase_cells(row, 0, self.cols),
+            },
+            'L' if (self.scroll_top..=self.scroll_bottom).contains(&row) => self.scroll_down_from(row, n(0)),
+            'M' if (self.scroll_top..=self.scroll_bottom).contains(&row) => self.scroll_up_from(row, n(0)),
+            '@' => {
+                let blank = self.blank();
+                let line = &mut self.grid[row];
+                for _ in 0..n(0).min(self.cols - col) {
+                    line.insert(col, blank);
+                    line.pop();
+                }
+            }
+            'P' => {
+                let blank = self.blank();
+                let line = &mut self.grid[row];
+                for _ in 0..n(0).min(self.cols - col) {
+                    line.remove(col);
+                    line.push(blank);
+                }
+            }
+            'X' => self.erase_cells(row, col, (col + n(0)).min(self.cols)),
+            'S' => self.scroll_up_from(self.scroll_top, n(0)),
+            'T' => self.scroll_down_from(self.scroll_top, n(0)),
+            'm' => self.style.apply_sgr(&params),
+            'r' => {
+                let top = n(0) - 1;
+                let bottom = params.get(1).copied().filter(|&v| v > 0).map_or(self.rows, |v| v as usize) - 1;
+                if top < bottom && bottom < self.rows {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.move_to(0, 0);
+                }
+            }
+            'h' | 'l' if private => {
+                let enable = action == 'h';
+                for &mode in &params {
+                    match mode {
+                        1 => self.app_cursor_keys = enable,
+                        25 => self.cursor_visible = enable,
+                        47 | 1047 | 1049 => self.set_alternate_screen(enable),
+                        _ => {}
+                    }
+                }
+            }
+            's' => self.saved_cursor = self.cursor,
+            'u' => self.move_to(self.saved_cursor.0, self.saved_cursor.1),
+            'n' if params.first() == Some(&6) => {
+                let reply = format!("\x1b[{};{}R", row + 1, col + 1);
+                self.replies.extend_from_slice(reply.as_bytes());
+            }
+            'c' if !private => self.replies.extend_from_slice(b"\x1b[?1;2c"),
+            _ => {}
+        }
+    }
+
+    fn set_alternate_screen(&mut self, enable: bool) {
+        if enable && self.primary.is_none() {
+            self.saved_cursor = self.cursor;
+            let blank = vec![vec![Cell::default(); self.cols]; self.rows];
+            self.primary = Some(std::mem::replace(&mut self.grid, blank));
+        } else if !enable {
+            if let Some(primary) = self.primary.take() {
+                self.grid = primary;
+                self.move_to(self.saved_cursor.0, self.saved_cursor.1);
+            }
+        }
+    }
+
+    fn blank(&self) -> Cell {
+        Cell {
+            ch: ' ',
+            mark: None,
+            style: Style {
+                bg: self.style.bg,
+                ..Style::default()
+            },
+        }
+    }
+
+    fn move_to(&mut self, row: usize, col: usize) {
+        self.cursor = (row.min(self.rows - 1), col.min(self.cols - 1));
+        self.wrap_pending = false;
+    }
+
+    fn put_char(&mut self, ch: char) {
+        let width = ch.width().unwrap_or(0);
+        if width == 0 {
+            self.put_mark(ch);
+            return;
+        }
+        // A wide character that does not fit on the line goes on the next
+        let no_room = width == 2 && self.cursor.1 + 1 >= self.cols && self.cols > 1;
+        if self.wrap_pending || no_room {
+            self.cursor.1 = 0;
+            self.linefeed();
+        }
+        let (row, col) = self.cursor;
+        let width = width.min(self.cols - col);
+        for col in col..col + width {
+            self.break_wide(row, col);
+        }
+        self.grid[row][col] = Cell { ch, mark: None, style: self.style };
+        if width == 2 {
+            self.grid[row][col + 1] = Cell { ch: WIDE_TAIL, mark: None, style: self.style };
+        }
+        if col + width >= self.cols {
+            self.cursor.1 = self.cols - 1;
+            self.wrap_pending = true;
+        } else {
+            self.cursor.1 += width;
+        }
+    }
+
+    /// Adds a combining mark to the character before the cursor; only the
+    /// first mark of a character is kept.
+    fn put_mark(&mut self, mark: char) {
+        let (row, col) = self.cursor;
+        let mut col = if self.wrap_pending { col } else { col.saturating_sub(1) };
+        if self.grid[row][col].ch == WIDE_TAIL && col > 0 {
+            col -= 1;
+        }
+        let cell = &mut self.grid[row][col];
+        cell.mark = cell.mark.or(Some(mark));
+    }
+
+    /// Blanks the other half of a wide character about to be half
+    /// overwritten at `col`.
+    fn break_wide(&mut self, row: usize, col: usize) {
+        let line = &mut self.grid[row];
+        if line[col].ch == WIDE_TAIL && col > 0 {
+            line[col - 1].ch = ' ';
+            line[col - 1].mark = None;
+        } else if line[col].is_wide() && col + 1 < line.len() && line[col + 1].ch == WIDE_TAIL {
+            line[col + 1].ch = ' ';
+        }
+    }
+
+    fn linefeed(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor.0 == self.scroll_bottom {
+            self.scroll_up_from(self.scroll_top, 1);
+        } else if self.cursor.0 + 1 < self.rows {
+            self.cursor.0 += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor.0 == self.scroll_top {
+            self.scroll_down_from(self.scroll_top, 1);
+        } else if self.cursor.0 > 0 {
+            self.cursor.0 -= 1;
+        }
+    }
+
+    /// Scrolls the lines from `top` to the bottom of the scroll region up,
+    /// filling the freed lines at the bottom with blanks.
+    fn scroll_up_from(&mut self, top: usize, count: usize) {
+        let blank = vec![self.blank(); self.cols];
+        for _ in 0..count.min(self.scroll_bottom + 1 - top) {
+            self.grid.remove(top);
+            self.grid.insert(self.scroll_bottom, blank.clone());
+        }
+    }
+
+    fn scroll_down_from(&mut self, top: usize, count: usize) {
+        let blank = vec![self.blank(); self.cols];
+        for _ in 0..count.min(self.scroll_bottom + 1 - top) {
+            self.grid.remove(self.scroll_bottom);
+            self.grid.insert(top, blank.clone());
+        }
+    }
+
+    fn erase_cells(&mut self, row: usize, from: usize, to: usize) {
+        let blank = self.blank();
+        for cell in &mut self.grid[row][from.min(self.cols)..to.min(self.cols)] {
+            *cell = blank;
+        }
+    }
+
+    fn erase_rows(&mut self, from: usize, to: usize) {
+        for row in from..to.min(self.rows) {
+            self.erase_cells(row, 0, self.cols);
+        }
+    }
+}
+
+/// Lets crossterm commands draw into the screen, as into a terminal.
+impl io::Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.process(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(rows: usize, cols: usize, input: &str) -> Screen {
+        let mut screen = Screen::new(rows, cols);
+        screen.process(input.as_bytes());
+        screen
+    }
+
+    fn text(screen: &Screen, row: usize) -> String {
+        screen.row(row).iter().map(|cell| cell.to_string()).collect::<String>().trim_end().to_string()
+    }
+
+    #[test]
+    fn writes_wraps_and_scrolls() {
+        let screen = run(2, 4, "abcdef\r\nxy");
+        assert_eq!(text(&screen, 0), "ef");
+        assert_eq!(text(&screen, 1), "xy");
+        assert_eq!(screen.cursor(), Some((1, 2)));
+
+        // The last column waits for the next character before wrapping
+        let screen = run(2, 4, "abcd");
+        assert_eq!(screen.cursor(), Some((0, 3)));
+        assert_eq!(text(&screen, 1), "");
+    }
+
+    #[test]
+    fn moves_the_cursor_and_erases() {
+        let mut screen = run(3, 5, "11111\r\n22222\r\n33333");
+        screen.process(b"\x1b[2;3H\x1b[K");
+        assert_eq!(text(&screen, 1), "22");
+        screen.process(b"\x1b[1J");
+        assert_eq!(text(&screen, 0), "");
+        assert_eq!(text(&screen, 1), "");
+        assert_eq!(text(&screen, 2), "33333");
+        screen.process(b"\x1b[3;2H\x1b[2P");
+        assert_eq!(text(&screen, 2), "333");
+        screen.process(b"\x1b[99;99H\x1b[6n");
+        assert_eq!(screen.take_replies(), b"\x1b[3;5R");
+    }
+
+    #[test]
+    fn applies_styles_to_written_cells() {
+        let screen = run(1, 4, "\x1b[1;31ma\x1b[0mb");
+        let cells = screen.row(0);
+        assert!(cells[0].style.bold);
+        assert_eq!(cells[0].style.fg, Color::DarkRed);
+        assert_eq!(cells[1].style, Style::default());
+    }
+
+    #[test]
+    fn handles_wide_characters_and_marks() {
+        let screen = run(2, 3, "a日本e\u{301}");
+        assert_eq!(text(&screen, 0), "a日");
+        assert_eq!(screen.row(0)[2].ch, WIDE_TAIL);
+        assert_eq!(text(&screen, 1), "本e\u{301}");
+
+        // Overwriting half of a wide character blanks the other half
+        let screen = run(1, 4, "日\x1b[1Gx");
+        assert_eq!(text(&screen, 0), "x");
+    }
+
+    #[test]
+    fn alternate_screen_restores_the_primary() {
+        let mut screen = run(2, 4, "main");
+        screen.process(b"\x1b[?1049h\x1b[Hfull");
+        assert_eq!(text(&screen, 0), "full");
+        screen.process(b"\x1b[?1049l");
+        assert_eq!(text(&screen, 0), "main");
+    }
+
+    #[test]
+    fn scroll_region_keeps_lines_outside() {
+        let mut screen = run(4, 2, "a\r\nb\r\nc\r\nd");
+        screen.process(b"\x1b[2;3r\x1b[3;1H\n");
+        let rows: Vec<_> = (0..4).map(|row| text(&screen, row)).collect();
+        assert_eq!(rows, ["a", "c", "", "d"]);
+    }
+
+    #[test]
+    fn skips_osc_and_split_utf8() {
+        let mut screen = Screen::new(1, 6);
+        screen.process(b"\x1b]0;title\x07a");
+        let bytes = "é".as_bytes();
+        screen.process(&bytes[..1]);
+        screen.process(&bytes[1..]);
+        screen.process(b"\xffb");
+        assert_eq!(text(&screen, 0), "aé\u{fffd}b");
+    }
+}